@@ -1,3 +1,4 @@
+use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
 
@@ -7,12 +8,112 @@ use std::cmp::Ordering;
 ///
 /// By deriving `Serialize` and `Deserialize`, `serde` can use this struct for
 /// (de)serialization.
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
 pub struct Animal {
     pub name: String,
     pub species: String,
     pub strength: usize,
     pub carnivore: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sex: Option<Sex>,
+    /// Animals without a breeding status are treated as adults
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub breeding_status: Option<BreedingStatus>,
+    /// The day the animal arrived at the zoo, as `YYYY-MM-DD`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub arrival: Option<NaiveDate>,
+    /// The habitat the animal needs, e.g. `"arctic"` or `"desert"`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub habitat: Option<String>,
+    /// Animals without health information are treated as healthy
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub health: Option<Health>,
+}
+
+/// How strong an injured animal counts as, in percent of its strength.
+pub const INJURED_STRENGTH_PERCENT: usize = 50;
+
+/// `Health` of an `Animal`.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Health {
+    /// Injured animals can't defend themselves as well
+    #[serde(default)]
+    pub injured: bool,
+    /// Contagious animals are kept away from all other animals
+    #[serde(default)]
+    pub contagious: bool,
+    /// The medication the animal gets, e.g. `"antibiotics"`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub medication: Option<String>,
+    /// The day the animal is due for a check by the vet, as `YYYY-MM-DD`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vet_check: Option<NaiveDate>,
+}
+
+/// `Sex` of an `Animal`.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Sex {
+    Male,
+    Female,
+}
+
+/// `BreedingStatus` of an `Animal`.
+///
+/// In JSON this is `"juvenile"`, `"adult"` or `{ "breeding": "PARTNER" }`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum BreedingStatus {
+    /// Too young to breed
+    Juvenile,
+    /// Adult, but not part of a breeding pair
+    Adult,
+    /// Adult and breeding with the animal of the same species with the given
+    /// name
+    Breeding(String),
+}
+
+impl Animal {
+    /// Animals are adults, unless they are known to be juveniles.
+    pub fn is_adult(&self) -> bool {
+        self.breeding_status != Some(BreedingStatus::Juvenile)
+    }
+
+    /// The name of the breeding partner of `self`, if any.
+    pub fn partner(&self) -> Option<&str> {
+        match &self.breeding_status {
+            Some(BreedingStatus::Breeding(partner)) => Some(partner),
+            _ => None,
+        }
+    }
+
+    /// Animals are healthy, unless they are known to be injured.
+    pub fn is_injured(&self) -> bool {
+        self.health.as_ref().is_some_and(|health| health.injured)
+    }
+
+    /// Animals are healthy, unless they are known to be contagious.
+    pub fn is_contagious(&self) -> bool {
+        self.health.as_ref().is_some_and(|health| health.contagious)
+    }
+
+    /// The day `self` is due for a check by the vet, if known.
+    pub fn vet_check(&self) -> Option<NaiveDate> {
+        self.health.as_ref().and_then(|health| health.vet_check)
+    }
+
+    /// `effective_strength` is how strong `self` counts as prey: injured
+    /// animals can't defend themselves and count as weaker.
+    ///
+    /// Predators always count with their full `strength`, since an injured
+    /// predator recovers sooner than anyone notices.
+    pub fn effective_strength(&self) -> usize {
+        if self.is_injured() {
+            self.strength * INJURED_STRENGTH_PERCENT / 100
+        } else {
+            self.strength
+        }
+    }
 }
 
 // You can implement traits like `PartialEq` for your types.
@@ -21,8 +122,7 @@ pub struct Animal {
 // project, we only care about the `species` when comparing animals.
 impl PartialEq for Animal {
     fn eq(&self, other: &Self) -> bool {
-        let _ = other; // suppress warning
-        false
+        self.species == other.species
     }
 }
 
@@ -30,8 +130,7 @@ impl PartialEq for Animal {
 // the `Ord` documentation for ideas how to implement this.
 impl Ord for Animal {
     fn cmp(&self, other: &Self) -> Ordering {
-        let _ = other; // suppress warning
-        Ordering::Equal
+        self.strength.cmp(&other.strength)
     }
 }
 
@@ -54,3 +153,108 @@ impl PartialOrd for Animal {
         Some(self.cmp(other))
     }
 }
+
+/// `FieldKind` is the type of a field of `Animal`, as seen by filter
+/// expressions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FieldKind {
+    Number,
+    Text,
+    Bool,
+    Date,
+}
+
+impl std::fmt::Display for FieldKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Number => write!(f, "number"),
+            Self::Text => write!(f, "text"),
+            Self::Bool => write!(f, "boolean"),
+            Self::Date => write!(f, "date"),
+        }
+    }
+}
+
+/// `FieldValue` is the value of a field of a specific `Animal`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FieldValue<'a> {
+    Number(usize),
+    Text(&'a str),
+    Bool(bool),
+    /// `None` if the date isn't known. It never matches any comparison.
+    Date(Option<NaiveDate>),
+}
+
+// If you add a field to `Animal`, add it here as well, so that it can be used
+// in filter expressions.
+impl Animal {
+    /// The names of all fields that can be accessed with `Animal::field`.
+    pub const FIELDS: &'static [&'static str] = &[
+        "name",
+        "species",
+        "strength",
+        "carnivore",
+        "sex",
+        "breeding_status",
+        "arrival",
+        "habitat",
+        "injured",
+        "contagious",
+        "vet_check",
+    ];
+
+    /// `field_kind` returns the type of the field `name`, or `None` if there
+    /// is no such field.
+    pub fn field_kind(name: &str) -> Option<FieldKind> {
+        match name {
+            "name" | "species" | "sex" | "breeding_status" | "habitat" => Some(FieldKind::Text),
+            "arrival" | "vet_check" => Some(FieldKind::Date),
+            "strength" => Some(FieldKind::Number),
+            "carnivore" | "injured" | "contagious" => Some(FieldKind::Bool),
+            _ => None,
+        }
+    }
+
+    /// `field` returns the value of the field `name`, or `None` if there is no
+    /// such field.
+    pub fn field(&self, name: &str) -> Option<FieldValue<'_>> {
+        match name {
+            "name" => Some(FieldValue::Text(&self.name)),
+            "species" => Some(FieldValue::Text(&self.species)),
+            "strength" => Some(FieldValue::Number(self.strength)),
+            "carnivore" => Some(FieldValue::Bool(self.carnivore)),
+            "sex" => Some(FieldValue::Text(match self.sex {
+                Some(Sex::Male) => "male",
+                Some(Sex::Female) => "female",
+                None => "unknown",
+            })),
+            "breeding_status" => Some(FieldValue::Text(match self.breeding_status {
+                Some(BreedingStatus::Juvenile) => "juvenile",
+                Some(BreedingStatus::Adult) => "adult",
+                Some(BreedingStatus::Breeding(_)) => "breeding",
+                None => "unknown",
+            })),
+            "arrival" => Some(FieldValue::Date(self.arrival)),
+            "habitat" => Some(FieldValue::Text(
+                self.habitat.as_deref().unwrap_or("unknown"),
+            )),
+            "injured" => Some(FieldValue::Bool(self.is_injured())),
+            "contagious" => Some(FieldValue::Bool(self.is_contagious())),
+            "vet_check" => Some(FieldValue::Date(self.vet_check())),
+            _ => None,
+        }
+    }
+}
+
+/// `animal` builds an animal for tests, with only the fields most tests care
+/// about.
+#[cfg(test)]
+pub(crate) fn animal(name: &str, species: &str, strength: usize, carnivore: bool) -> Animal {
+    Animal {
+        name: String::from(name),
+        species: String::from(species),
+        strength,
+        carnivore,
+        ..Animal::default()
+    }
+}
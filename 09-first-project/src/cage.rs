@@ -1,3 +1,5 @@
+use crate::error::Error;
+use crate::species;
 use crate::{Animal, Result};
 use serde::{Deserialize, Serialize};
 
@@ -11,6 +13,14 @@ use serde::{Deserialize, Serialize};
 #[derive(Serialize, Deserialize, Clone, Default, Debug, Eq, PartialEq)]
 pub struct Cage {
     pub animals: Vec<Animal>,
+    /// The habitat the cage provides. Without one, it is derived from the
+    /// animals, see `habitat`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub habitat: Option<String>,
+    /// Why the cage is laid out like this, e.g. why a species got a cage of
+    /// its own
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub notes: Vec<String>,
 }
 
 // Nothing to change here
@@ -21,7 +31,34 @@ pub struct Cage {
 // this you can for example write `let x: Cage = animal_vec.into()`.
 impl From<Vec<Animal>> for Cage {
     fn from(animals: Vec<Animal>) -> Self {
-        Self { animals }
+        Self {
+            animals,
+            ..Self::default()
+        }
+    }
+}
+
+/// `CageKind` describes which kind of animals live in a `Cage`.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum CageKind {
+    /// Only carnivores
+    Carnivores,
+    /// Carnivores together with the herbivores that are too strong for them
+    Mixed,
+    /// Only herbivores
+    Herbivores,
+    /// No animals at all
+    Empty,
+}
+
+impl std::fmt::Display for CageKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Carnivores => f.pad("carnivores"),
+            Self::Mixed => f.pad("mixed"),
+            Self::Herbivores => f.pad("herbivores"),
+            Self::Empty => f.pad("empty"),
+        }
     }
 }
 
@@ -36,7 +73,7 @@ impl Cage {
     /// This method should be a one-liner. Take a look at the `Vec`
     /// documentation to find out how to `extend` one vector with another.
     pub fn move_from(&mut self, cage: Cage) {
-        let _ = cage; // suppress warning
+        self.animals.extend(cage.animals);
     }
 
     /// `weakest` will return the weakest animal in `self`.
@@ -46,7 +83,15 @@ impl Cage {
     /// Since you have implemented `Ord` on `Animal`, you can use the `min()`
     /// method from the `Iterator` trait.
     pub fn weakest(&self) -> Option<&Animal> {
-        None
+        self.animals.iter().min()
+    }
+
+    /// `most_vulnerable` is the animal in `self` with the lowest
+    /// `effective_strength`, which is the `weakest` unless some are injured.
+    pub fn most_vulnerable(&self) -> Option<&Animal> {
+        self.animals
+            .iter()
+            .min_by_key(|animal| animal.effective_strength())
     }
 
     /// `strongest` will return the strongest animal in `self`.
@@ -56,13 +101,14 @@ impl Cage {
     /// Since you have implemented `Ord` on `Animal`, you can use the `max()`
     /// method from the `Iterator` trait.
     pub fn strongest(&self) -> Option<&Animal> {
-        None
+        self.animals.iter().max()
     }
 
     /// `fits` will determine if the passed herbivore can be put in `self`.
     ///
     /// A herbivore can be placed into a cage, if the strongest animal in the
-    /// cage is weaker (`<`) than the herbivore.
+    /// cage is weaker (`<`) than the herbivore. Injured herbivores count with
+    /// their `effective_strength`.
     ///
     /// # Hint
     ///
@@ -71,7 +117,9 @@ impl Cage {
     /// converts the type in the option to any type you want.
     pub fn fits(&self, herbivore: &Animal) -> bool {
         assert!(!herbivore.carnivore);
-        false
+        self.strongest()
+            .map(|strongest| strongest.strength < herbivore.effective_strength())
+            .unwrap_or(true)
     }
 
     /// BONUS: `deliver_food` will move the food from `food_cage` to `self`.
@@ -81,7 +129,91 @@ impl Cage {
     /// This method errors, if an animal in the `food_cage` is stronger than
     /// the weakest animal in `self`.
     pub fn deliver_food(&mut self, food_cage: Cage) -> Result<()> {
-        let _ = food_cage; // suppress warning
+        if let (Some(food), Some(weakest)) = (food_cage.strongest(), self.weakest()) {
+            if food.strength >= weakest.strength {
+                return Err(Error::FoodTooStrong {
+                    food: Box::new(food.clone()),
+                    predator: Box::new(weakest.clone()),
+                });
+            }
+        }
+        self.move_from(food_cage);
         Ok(())
     }
+
+    /// `habitat` is the habitat of `self`: the one it was given, or else the
+    /// one of its predator, or of any other animal if the predator doesn't
+    /// need a specific habitat.
+    pub fn habitat(&self) -> Option<&str> {
+        self.habitat
+            .as_deref()
+            .or_else(|| {
+                self.predator()
+                    .and_then(|predator| predator.habitat.as_deref())
+            })
+            .or_else(|| {
+                self.animals
+                    .iter()
+                    .find_map(|animal| animal.habitat.as_deref())
+            })
+    }
+
+    /// `suits` determines if `self` provides the habitat `animal` needs.
+    ///
+    /// Animals without a habitat, and cages without one, suit everything.
+    /// Habitats are compared like species names, so `"Arctic"` suits
+    /// `"arctic"`.
+    pub fn suits(&self, animal: &Animal) -> bool {
+        match (self.habitat(), animal.habitat.as_deref()) {
+            (Some(habitat), Some(needed)) => {
+                species::normalize(habitat) == species::normalize(needed)
+            }
+            _ => true,
+        }
+    }
+
+    /// `suits_all` determines if `self` provides the habitat every animal of
+    /// `group` needs.
+    pub fn suits_all(&self, group: &Cage) -> bool {
+        group.animals.iter().all(|animal| self.suits(animal))
+    }
+
+    /// `kind` determines which kind of animals live in `self`.
+    pub fn kind(&self) -> CageKind {
+        let carnivores = self.animals.iter().any(|animal| animal.carnivore);
+        let herbivores = self.animals.iter().any(|animal| !animal.carnivore);
+        match (carnivores, herbivores) {
+            (true, true) => CageKind::Mixed,
+            (true, false) => CageKind::Carnivores,
+            (false, true) => CageKind::Herbivores,
+            (false, false) => CageKind::Empty,
+        }
+    }
+
+    /// `predator` returns the strongest carnivore in `self`.
+    pub fn predator(&self) -> Option<&Animal> {
+        self.animals.iter().filter(|animal| animal.carnivore).max()
+    }
+
+    /// `safety_margin` is the difference in strength between the weakest prey
+    /// and the `predator` of `self`.
+    ///
+    /// Every animal with a different species than the predator counts as prey,
+    /// with its `effective_strength`. Animals of the `food` species are
+    /// supposed to be eaten, so they count neither as prey nor as predator.
+    /// The cage is safe as long as the margin isn't negative: at 0, the
+    /// predator is only as strong as its prey and can't eat it.
+    ///
+    /// Returns `None` if there is no predator or nothing to prey on.
+    pub fn safety_margin(&self, food: Option<&str>) -> Option<i64> {
+        let is_food =
+            |animal: &Animal| food.is_some_and(|food| species::same_species(&animal.species, food));
+        let residents = || self.animals.iter().filter(|animal| !is_food(animal));
+        let predator = residents().filter(|animal| animal.carnivore).max()?;
+        let prey = residents()
+            .filter(|animal| !species::same_species(&animal.species, &predator.species))
+            .map(Animal::effective_strength)
+            .min()?;
+        Some(prey as i64 - predator.strength as i64)
+    }
 }
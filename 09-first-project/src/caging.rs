@@ -0,0 +1,714 @@
+//! Caging with the requirements of the species taken into account.
+//!
+//! `cage_em_all` only knows the rules from the README. `Caging` runs it and
+//! then adjusts the layout to the requirements the `Catalogue` describes,
+//! without breaking any of the rules: animals are only ever moved out of a
+//! cage into a cage of their own, or next to a predator they are too strong
+//! for. Requirements that can't be met are reported as `Note`s.
+//!
+//! New arrivals have to spend some time in quarantine first. They are kept out
+//! of the shared cages until they are released. Contagious animals are
+//! isolated, each in a cage of its own, until they are healthy again.
+//!
+//! With a `CostModel`, the layout is the cheapest one `optimise` finds,
+//! instead of the one `cage_em_all` finds. With a maximum risk, layouts the
+//! `RiskModel` deems riskier are rejected.
+
+use crate::catalogue::Catalogue;
+use crate::cost::{optimise, CostBreakdown, CostModel};
+use crate::placement::Placement;
+use crate::risk::{RiskModel, RiskReport};
+use crate::species::{normalize, same_species};
+use crate::{cage_em_all_with, Animal, Cage, Error, Result, Sex};
+use chrono::{Duration, NaiveDate};
+use serde::Serialize;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Display, Formatter};
+
+/// `Note` is a requirement of a species the layout doesn't meet.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub enum Note {
+    /// There are fewer animals of the species than it needs to be kept in a
+    /// group
+    Understaffed {
+        species: String,
+        animals: usize,
+        min_group: usize,
+    },
+    /// A herbivore species didn't join a predator it is too strong for,
+    /// since the cage of the predator doesn't provide its habitat
+    HabitatMismatch {
+        species: String,
+        habitat: String,
+        /// The position of the cage in the layout, starting at 1
+        cage: usize,
+        cage_habitat: String,
+    },
+    /// A breeding pair couldn't be housed together
+    SeparatedPair {
+        animal: String,
+        partner: String,
+        species: String,
+        reason: &'static str,
+    },
+}
+
+/// How many days new arrivals spend in quarantine, if not configured
+/// otherwise.
+pub const DEFAULT_QUARANTINE_DAYS: i64 = 30;
+
+/// `Quarantine` configures the quarantine of new arrivals.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Quarantine {
+    /// How many days an animal stays in quarantine after its arrival
+    pub days: i64,
+    /// The day the layout is made for
+    pub today: NaiveDate,
+}
+
+impl Quarantine {
+    /// `release` is the day `animal` leaves the quarantine, or `None` if it
+    /// doesn't have to be quarantined (anymore).
+    pub fn release(&self, animal: &Animal) -> Option<NaiveDate> {
+        let release = animal.arrival? + Duration::days(self.days);
+        (release > self.today).then_some(release)
+    }
+}
+
+/// `QuarantineCage` holds a single animal in quarantine.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct QuarantineCage {
+    pub animal: Animal,
+    pub release: NaiveDate,
+}
+
+/// `Layout` is the result of `Caging::cage`.
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+pub struct Layout {
+    pub cages: Vec<Cage>,
+    /// New arrivals, which don't join the `cages` yet, ordered by their
+    /// release
+    pub quarantine: Vec<QuarantineCage>,
+    /// Contagious animals, each in a cage of its own
+    pub isolation: Vec<Animal>,
+    pub notes: Vec<Note>,
+    /// What the `cages` cost, if `Caging` has a cost model
+    pub cost: Option<CostBreakdown>,
+    /// How risky the `cages` are
+    pub risk: RiskReport,
+}
+
+/// `Caging` configures how animals are caged.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Caging {
+    /// The requirements of the species
+    pub catalogue: Catalogue,
+    /// `None` puts new arrivals into the shared cages right away
+    pub quarantine: Option<Quarantine>,
+    /// `None` uses as few cages as possible, regardless of their cost
+    pub cost: Option<CostModel>,
+    /// Which cage a herbivore goes into, if it fits into several
+    pub placement: Placement,
+    /// How risky a cage is
+    pub risk: RiskModel,
+    /// `None` accepts layouts regardless of their risk
+    pub max_risk: Option<f64>,
+}
+
+impl Caging {
+    /// `cage` puts the `animals` into cages, like `cage_em_all`.
+    ///
+    /// # Errors
+    ///
+    /// This method errors in the same cases as `cage_em_all`, with
+    /// `Error::Inconsistent` if animals contradict the `catalogue` and with
+    /// `Error::TooRisky` if the layout is riskier than `max_risk`.
+    pub fn cage(&self, animals: Vec<Animal>, food: Option<&str>) -> Result<Layout> {
+        // Records contradicting the catalogue are most likely typos
+        let conflicts = self.catalogue.check(&animals);
+        if !conflicts.is_empty() {
+            return Err(Error::Inconsistent(conflicts));
+        }
+
+        let is_food =
+            |animal: &Animal| food.is_some_and(|food| same_species(&animal.species, food));
+        let notes = self.understaffed(animals.iter().filter(|animal| !is_food(animal)));
+
+        let mut quarantine = vec![];
+        let mut isolation = vec![];
+        let mut residents = vec![];
+        for animal in animals {
+            if animal.is_contagious() {
+                isolation.push(animal);
+                continue;
+            }
+            match self.quarantine.and_then(|q| q.release(&animal)) {
+                Some(release) => quarantine.push(QuarantineCage { animal, release }),
+                None => residents.push(animal),
+            }
+        }
+        quarantine.sort_by_key(|cage| cage.release);
+
+        let safe_enough = |cages: &[Cage]| {
+            self.max_risk
+                .is_none_or(|max_risk| self.risk.assess(self, cages, food).total <= max_risk)
+        };
+        let cages = match &self.cost {
+            Some(model) => optimise(model, &self.placement, residents, food, safe_enough)?,
+            None => cage_em_all_with(residents, food, &self.placement)?,
+        };
+        let mut layout = Layout {
+            cages,
+            quarantine,
+            isolation,
+            notes,
+            cost: None,
+            risk: RiskReport::default(),
+        };
+        self.separate(&mut layout.cages, is_food);
+        let separated = self.separated_pairs(&layout, is_food);
+        layout.notes.extend(separated);
+        // Every cage keeps the habitat it was built for, and the reason why a
+        // species got a cage of its own
+        for cage in &mut layout.cages {
+            cage.habitat = cage.habitat().map(str::to_string);
+        }
+        for (i, note) in habitat_mismatches(&layout.cages) {
+            layout.cages[i].notes.push(note.to_string());
+            layout.notes.push(note);
+        }
+        layout.cost = self
+            .cost
+            .as_ref()
+            .map(|model| model.breakdown(&layout.cages));
+        layout.risk = self.risk.assess(self, &layout.cages, food);
+        if let Some(max_risk) = self.max_risk.filter(|&max| layout.risk.total > max) {
+            return Err(Error::TooRisky {
+                risk: layout.risk.total,
+                max_risk,
+            });
+        }
+        Ok(layout)
+    }
+
+    /// Species with fewer animals than their minimum group size.
+    fn understaffed<'a>(&self, animals: impl Iterator<Item = &'a Animal>) -> Vec<Note> {
+        let mut species: BTreeMap<String, (&str, usize)> = BTreeMap::new();
+        for animal in animals {
+            species
+                .entry(normalize(&animal.species))
+                .or_insert((&animal.species, 0))
+                .1 += 1;
+        }
+
+        species
+            .into_values()
+            .filter_map(|(species, animals)| {
+                let social = self.catalogue.info(species)?.social;
+                (!social.solitary && animals < social.min_group).then(|| Note::Understaffed {
+                    species: species.to_string(),
+                    animals,
+                    min_group: social.min_group,
+                })
+            })
+            .collect()
+    }
+
+    /// `conflict` is true if `a` and `b` mustn't share a cage because of the
+    /// social requirements of their species.
+    pub(crate) fn conflict(&self, a: &Animal, b: &Animal) -> bool {
+        let social = match self.catalogue.info(&a.species) {
+            Some(info) if same_species(&a.species, &b.species) => info.social,
+            _ => return false,
+        };
+        let males = |animal: &Animal| animal.sex == Some(Sex::Male) && animal.is_adult();
+        !partners(a, b) && (social.solitary || social.single_male && males(a) && males(b))
+    }
+
+    /// Moves animals out of cages they can't share with their cagemates.
+    ///
+    /// The strongest animals stay, so that the predator of a cage and with it
+    /// the safety of its prey doesn't change. Breeding partners are moved
+    /// together, into the cage the `placement` prefers.
+    fn separate(&self, cages: &mut Vec<Cage>, is_food: impl Fn(&Animal) -> bool) {
+        let mut leaving = vec![];
+        for cage in cages.iter_mut() {
+            let animals = &cage.animals;
+            let mut units = units(animals);
+            units.sort_by_key(|unit| Reverse(unit.iter().map(|&i| animals[i].strength).max()));
+
+            let mut stay = vec![false; animals.len()];
+            for unit in units {
+                let conflict = unit.iter().any(|&i| {
+                    (0..animals.len()).any(|j| {
+                        stay[j]
+                            && !is_food(&animals[i])
+                            && !is_food(&animals[j])
+                            && self.conflict(&animals[i], &animals[j])
+                    })
+                });
+                if conflict {
+                    leaving.push(unit.iter().map(|&i| animals[i].clone()).collect::<Vec<_>>());
+                } else {
+                    unit.iter().for_each(|&i| stay[i] = true);
+                }
+            }
+
+            let mut stay = stay.into_iter();
+            cage.animals
+                .retain(|_| stay.next().expect("one flag per animal"));
+        }
+
+        for unit in leaving {
+            // Herbivores may still join a predator that is too weak for them,
+            // as long as they get along with the animals there
+            let weakest = unit.iter().min().expect("units aren't empty");
+            let cage = self.placement.choose(cages, weakest, |_, cage| {
+                unit.iter().all(|animal| {
+                    !animal.carnivore
+                        && cage.predator().is_some()
+                        && cage.fits(animal)
+                        && cage.suits(animal)
+                        && !cage
+                            .animals
+                            .iter()
+                            .any(|other| self.conflict(animal, other))
+                })
+            });
+            match cage {
+                Some(i) => cages[i].animals.extend(unit),
+                None => cages.push(Cage::from(unit)),
+            }
+        }
+    }
+
+    /// Breeding pairs that don't share a cage.
+    fn separated_pairs(&self, layout: &Layout, is_food: impl Fn(&Animal) -> bool) -> Vec<Note> {
+        let cages = &layout.cages;
+        let quarantined = layout.quarantine.iter().map(|cage| &cage.animal);
+        // Quarantined and isolated animals are alone, each in a cage of its own
+        let alone = quarantined.clone().chain(&layout.isolation);
+        let caged = || {
+            cages
+                .iter()
+                .enumerate()
+                .flat_map(|(i, cage)| cage.animals.iter().map(move |animal| (i, animal)))
+                .chain(
+                    alone
+                        .clone()
+                        .enumerate()
+                        .map(|(i, animal)| (cages.len() + i, animal)),
+                )
+        };
+        let in_quarantine = |animal: &Animal| {
+            quarantined.clone().any(|other| {
+                other.name == animal.name && same_species(&other.species, &animal.species)
+            })
+        };
+
+        let mut reported = BTreeSet::new();
+        let mut notes = vec![];
+        for (cage, animal) in caged() {
+            let name = match animal.partner() {
+                Some(name) => name,
+                None => continue,
+            };
+            let partner = caged().find(|(_, other)| {
+                other.name == name && same_species(&other.species, &animal.species)
+            });
+            let reason = match partner {
+                None => "there is no such animal of the same species",
+                Some((other, _)) if other == cage => continue,
+                Some((_, partner)) if in_quarantine(animal) || in_quarantine(partner) => {
+                    "one of them is in quarantine"
+                }
+                Some((_, partner)) if animal.is_contagious() || partner.is_contagious() => {
+                    "one of them is contagious"
+                }
+                Some((_, partner)) if is_food(animal) || is_food(partner) => {
+                    "one of them is used as food"
+                }
+                Some(_) => "they can't share a cage without breaking the rules",
+            };
+
+            let mut pair = [animal.name.as_str(), name];
+            pair.sort_unstable();
+            if reported.insert((normalize(&animal.species), pair)) {
+                notes.push(Note::SeparatedPair {
+                    animal: animal.name.clone(),
+                    partner: name.to_string(),
+                    species: animal.species.clone(),
+                    reason,
+                });
+            }
+        }
+        notes
+    }
+}
+
+/// Herbivore species that live without a predator, although there is one they
+/// are too strong for in a cage with the wrong habitat, with the index of the
+/// cage they live in.
+fn habitat_mismatches(cages: &[Cage]) -> Vec<(usize, Note)> {
+    let mut notes = vec![];
+    for (j, cage) in cages
+        .iter()
+        .enumerate()
+        .filter(|(_, cage)| cage.predator().is_none())
+    {
+        // Like `relocate_animals`, look at the weakest animal of every species
+        let mut weakest: BTreeMap<String, &Animal> = BTreeMap::new();
+        for animal in &cage.animals {
+            let entry = weakest.entry(normalize(&animal.species)).or_insert(animal);
+            if animal.effective_strength() < entry.effective_strength() {
+                *entry = animal;
+            }
+        }
+
+        for herbivore in weakest.into_values() {
+            let mismatch = cages.iter().enumerate().find(|(_, other)| {
+                other
+                    .predator()
+                    .is_some_and(|predator| predator.strength < herbivore.effective_strength())
+                    && !other.suits(herbivore)
+            });
+            if let (Some((i, other)), Some(habitat)) = (mismatch, &herbivore.habitat) {
+                notes.push((
+                    j,
+                    Note::HabitatMismatch {
+                        species: herbivore.species.clone(),
+                        habitat: habitat.clone(),
+                        cage: i + 1,
+                        cage_habitat: other.habitat().unwrap_or_default().to_string(),
+                    },
+                ));
+            }
+        }
+    }
+    notes
+}
+
+/// `a` and `b` are a breeding pair, if one of them names the other as its
+/// partner.
+fn partners(a: &Animal, b: &Animal) -> bool {
+    same_species(&a.species, &b.species)
+        && (a.partner() == Some(b.name.as_str()) || b.partner() == Some(a.name.as_str()))
+}
+
+/// Groups the indices of `animals` into units that are moved together: single
+/// animals and breeding pairs.
+fn units(animals: &[Animal]) -> Vec<Vec<usize>> {
+    let mut assigned = vec![false; animals.len()];
+    let mut units = vec![];
+    for i in 0..animals.len() {
+        if assigned[i] {
+            continue;
+        }
+        let mut unit = vec![i];
+        if let Some(j) =
+            (i + 1..animals.len()).find(|&j| !assigned[j] && partners(&animals[i], &animals[j]))
+        {
+            assigned[j] = true;
+            unit.push(j);
+        }
+        units.push(unit);
+    }
+    units
+}
+
+impl Display for Note {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Understaffed {
+                species,
+                animals,
+                min_group,
+            } => write!(
+                f,
+                "species {} should be kept in groups of at least {}, but there are only {}",
+                species, min_group, animals
+            ),
+            Self::SeparatedPair {
+                animal,
+                partner,
+                species,
+                reason,
+            } => write!(
+                f,
+                "{} the {} can't be housed with its breeding partner {}: {}",
+                animal, species, partner, reason
+            ),
+            Self::HabitatMismatch {
+                species,
+                habitat,
+                cage,
+                cage_habitat,
+            } => write!(
+                f,
+                "species {} ({}) gets a cage of its own, since cage {} is {}",
+                species, habitat, cage, cage_habitat
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::animal::animal;
+    use crate::invariants::{self, eats, Violation};
+    use crate::{BreedingStatus, Health};
+
+    fn caging() -> Caging {
+        Caging {
+            catalogue: serde_json::from_str(
+                r#"{
+                    "Lion": { "social": { "single_male": true } },
+                    "Tiger": { "social": { "solitary": true } },
+                    "Rhino": { "social": { "solitary": true } },
+                    "Meerkat": { "social": { "min_group": 5 } }
+                }"#,
+            )
+            .unwrap(),
+            quarantine: None,
+            cost: None,
+            placement: Placement::default(),
+            risk: RiskModel::default(),
+            max_risk: None,
+        }
+    }
+
+    #[test]
+    fn solitary_species() {
+        let animals = vec![
+            animal("Shere Khan", "Tiger", 20, true),
+            animal("Rajah", "Tiger", 18, true),
+            animal("Rhonda", "Rhino", 30, false),
+            animal("Rocky", "Rhino", 25, false),
+            animal("Rita", "Rhino", 19, false),
+        ];
+        let layout = caging().cage(animals.clone(), None).unwrap();
+
+        for cage in &layout.cages {
+            let tigers = cage.animals.iter().filter(|a| a.species == "Tiger").count();
+            let rhinos = cage.animals.iter().filter(|a| a.species == "Rhino").count();
+            assert!(tigers <= 1 && rhinos <= 1, "{:?}", cage);
+            for predator in &cage.animals {
+                assert!(!cage.animals.iter().any(|prey| eats(predator, prey)));
+            }
+        }
+        assert_eq!(layout.cages.len(), 3);
+        assert!(layout.notes.is_empty());
+
+        // The split is fine for the catalogue, but not for the plain rules
+        assert_eq!(
+            invariants::check_with(&caging(), &animals, None, &layout.cages),
+            []
+        );
+        assert!(matches!(
+            invariants::check(&animals, None, &layout.cages)[..],
+            [
+                Violation::SplitSpecies { .. },
+                Violation::SplitSpecies { .. }
+            ]
+        ));
+    }
+
+    #[test]
+    fn understaffed_species() {
+        let layout = caging()
+            .cage(vec![animal("Timon", "Meerkat", 1, false)], None)
+            .unwrap();
+        assert_eq!(
+            layout.notes,
+            [Note::Understaffed {
+                species: String::from("Meerkat"),
+                animals: 1,
+                min_group: 5
+            }]
+        );
+    }
+
+    #[test]
+    fn breeding_pairs() {
+        let lion = |name, strength, sex, breeding_status| Animal {
+            sex: Some(sex),
+            breeding_status: Some(breeding_status),
+            ..animal(name, "Lion", strength, true)
+        };
+        let layout = caging()
+            .cage(
+                vec![
+                    lion("Leo", 20, Sex::Male, BreedingStatus::Adult),
+                    lion(
+                        "Simba",
+                        18,
+                        Sex::Male,
+                        BreedingStatus::Breeding("Nala".into()),
+                    ),
+                    lion("Nala", 12, Sex::Female, BreedingStatus::Adult),
+                    lion("Kovu", 8, Sex::Male, BreedingStatus::Juvenile),
+                    lion(
+                        "Sarabi",
+                        15,
+                        Sex::Female,
+                        BreedingStatus::Breeding("Mufasa".into()),
+                    ),
+                ],
+                None,
+            )
+            .unwrap();
+
+        let names: Vec<Vec<_>> = layout
+            .cages
+            .iter()
+            .map(|cage| cage.animals.iter().map(|a| a.name.as_str()).collect())
+            .collect();
+        assert_eq!(
+            names,
+            [vec!["Leo", "Kovu", "Sarabi"], vec!["Simba", "Nala"]]
+        );
+        assert_eq!(
+            layout.notes,
+            [Note::SeparatedPair {
+                animal: String::from("Sarabi"),
+                partner: String::from("Mufasa"),
+                species: String::from("Lion"),
+                reason: "there is no such animal of the same species"
+            }]
+        );
+    }
+
+    #[test]
+    fn quarantine() {
+        let date = |day| NaiveDate::from_ymd_opt(2024, 5, day);
+        let caging = Caging {
+            quarantine: Some(Quarantine {
+                days: 14,
+                today: date(20).unwrap(),
+            }),
+            ..caging()
+        };
+        let layout = caging
+            .cage(
+                vec![
+                    Animal {
+                        arrival: date(1),
+                        ..animal("Leo", "Lion", 20, true)
+                    },
+                    Animal {
+                        arrival: date(10),
+                        ..animal("Nala", "Lion", 12, true)
+                    },
+                    Animal {
+                        arrival: date(6),
+                        ..animal("Marty", "Zebra", 10, false)
+                    },
+                ],
+                None,
+            )
+            .unwrap();
+
+        let names: Vec<_> = layout
+            .cages
+            .iter()
+            .map(|cage| cage.animals[0].name.as_str())
+            .collect();
+        // Marty is released today
+        assert_eq!(names, ["Leo", "Marty"]);
+        let quarantine: Vec<_> = layout
+            .quarantine
+            .iter()
+            .map(|cage| (cage.animal.name.as_str(), cage.release))
+            .collect();
+        assert_eq!(quarantine, [("Nala", date(24).unwrap())]);
+    }
+
+    #[test]
+    fn habitats() {
+        let animal = |name, species, strength, carnivore, habitat: &str| Animal {
+            habitat: Some(habitat.to_string()),
+            ..animal(name, species, strength, carnivore)
+        };
+        let layout = caging()
+            .cage(
+                vec![
+                    animal("Nanook", "Polar Bear", 20, true, "arctic"),
+                    animal("Leo", "Lion", 30, true, "savanna"),
+                    animal("Humphrey", "Camel", 25, false, "desert"),
+                    animal("Bo", "Musk Ox", 40, false, "arctic"),
+                    animal("Marty", "Zebra", 35, false, "savanna"),
+                ],
+                None,
+            )
+            .unwrap();
+
+        let names: Vec<Vec<_>> = layout
+            .cages
+            .iter()
+            .map(|cage| cage.animals.iter().map(|a| a.name.as_str()).collect())
+            .collect();
+        assert_eq!(
+            names,
+            [vec!["Leo", "Marty"], vec!["Nanook", "Bo"], vec!["Humphrey"]]
+        );
+        assert_eq!(
+            layout.notes,
+            [Note::HabitatMismatch {
+                species: String::from("Camel"),
+                habitat: String::from("desert"),
+                cage: 2,
+                cage_habitat: String::from("arctic")
+            }]
+        );
+
+        // The cages keep their habitat and the reason in the solution
+        let habitats: Vec<_> = layout.cages.iter().map(|c| c.habitat.as_deref()).collect();
+        assert_eq!(habitats, [Some("savanna"), Some("arctic"), Some("desert")]);
+        assert_eq!(layout.cages[2].notes, [layout.notes[0].to_string()]);
+        let json = serde_json::to_value(&layout.cages[2]).unwrap();
+        assert_eq!(json["habitat"], "desert");
+        assert_eq!(
+            json["notes"][0],
+            "species Camel (desert) gets a cage of its own, since cage 2 is arctic"
+        );
+    }
+
+    #[test]
+    fn health() {
+        let layout = caging()
+            .cage(
+                vec![
+                    animal("Leo", "Lion", 20, true),
+                    Animal {
+                        health: Some(Health {
+                            injured: true,
+                            ..Health::default()
+                        }),
+                        ..animal("Marty", "Zebra", 30, false)
+                    },
+                    Animal {
+                        health: Some(Health {
+                            contagious: true,
+                            ..Health::default()
+                        }),
+                        ..animal("Gloria", "Hippo", 40, false)
+                    },
+                ],
+                None,
+            )
+            .unwrap();
+
+        // Injured, Marty only counts as 15 and isn't safe with Leo
+        let names: Vec<_> = layout
+            .cages
+            .iter()
+            .map(|cage| cage.animals[0].name.as_str())
+            .collect();
+        assert_eq!(names, ["Leo", "Marty"]);
+        assert_eq!(layout.isolation.len(), 1);
+        assert_eq!(layout.isolation[0].name, "Gloria");
+        assert!(layout.isolation[0].is_contagious());
+    }
+}
@@ -0,0 +1,400 @@
+//! A catalogue describing the species of the zoo.
+//!
+//! Every `Animal` record repeats the diet of its species, and nothing says how
+//! strong a species usually is. The catalogue describes every species once.
+//! Records may then omit the fields the catalogue knows, and records that
+//! contradict the catalogue are caught before they are caged.
+//!
+//! The catalogue is a JSON object mapping species names to their description.
+//! Every field is optional:
+//!
+//! ```json
+//! {
+//!     "Lion": {
+//!         "carnivore": true,
+//!         "strength": [10, 20],
+//!         "social": { "solitary": false, "min_group": 3 },
+//!         "habitat": "savanna",
+//!         "diet": { "food": "meat", "quantity": 7.5, "times": ["16:00"] },
+//!         "aliases": ["African Lion"]
+//!     }
+//! }
+//! ```
+
+use crate::consistency::Conflict;
+use crate::error::Error;
+use crate::feeding::Diet;
+use crate::species::Registry;
+use crate::{Animal, BreedingStatus, Health, Result, Sex};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::BTreeMap;
+
+/// `Social` describes how a species wants to be kept.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(default)]
+pub struct Social {
+    /// Adults of the species can't share a cage with each other
+    pub solitary: bool,
+    /// The species must be kept in groups of at least this size
+    pub min_group: usize,
+    /// Two adult males of the species can't share a cage
+    pub single_male: bool,
+}
+
+impl Default for Social {
+    fn default() -> Self {
+        Self {
+            solitary: false,
+            min_group: 1,
+            single_male: false,
+        }
+    }
+}
+
+/// `SpeciesInfo` is the description of a species in the `Catalogue`.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct SpeciesInfo {
+    pub carnivore: Option<bool>,
+    /// The typical strength of the species, as `[min, max]`
+    pub strength: Option<(usize, usize)>,
+    pub social: Social,
+    pub habitat: Option<String>,
+    /// What the animals of the species are fed, see `FeedingPlan`
+    ///
+    /// [`FeedingPlan`]: crate::feeding::FeedingPlan
+    pub diet: Option<Diet>,
+    pub aliases: Vec<String>,
+}
+
+impl SpeciesInfo {
+    /// `default_strength` is the strength assumed for animals of the species
+    /// whose record doesn't specify one.
+    ///
+    /// It is the pessimistic end of the typical range: carnivores are assumed
+    /// to be as strong as possible, herbivores as weak as possible, so a
+    /// missing strength never puts prey next to a predator.
+    pub fn default_strength(&self, carnivore: bool) -> Option<usize> {
+        self.strength
+            .map(|(min, max)| if carnivore { max } else { min })
+    }
+}
+
+/// `Record` is an animal as written in an inventory, where the fields the
+/// catalogue knows may be omitted.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Record {
+    pub name: String,
+    pub species: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub strength: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub carnivore: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sex: Option<Sex>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub breeding_status: Option<BreedingStatus>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub arrival: Option<NaiveDate>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub habitat: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub health: Option<Health>,
+}
+
+impl From<Animal> for Record {
+    fn from(animal: Animal) -> Self {
+        Self {
+            name: animal.name,
+            species: animal.species,
+            strength: Some(animal.strength),
+            carnivore: Some(animal.carnivore),
+            sex: animal.sex,
+            breeding_status: animal.breeding_status,
+            arrival: animal.arrival,
+            habitat: animal.habitat,
+            health: animal.health,
+        }
+    }
+}
+
+/// `inventory_schema` is the JSON Schema of an inventory, an array of
+/// `Record`s.
+pub fn inventory_schema() -> serde_json::Value {
+    let date = json!({ "type": "string", "format": "date" });
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "Inventory",
+        "type": "array",
+        "items": {
+            "type": "object",
+            "required": ["name", "species"],
+            "properties": {
+                "name": { "type": "string" },
+                "species": { "type": "string" },
+                "strength": {
+                    "type": "integer",
+                    "minimum": 0,
+                    "description": "may be omitted if the catalogue knows the species"
+                },
+                "carnivore": {
+                    "type": "boolean",
+                    "description": "may be omitted if the catalogue knows the species"
+                },
+                "sex": { "enum": ["male", "female"] },
+                "breeding_status": {
+                    "oneOf": [
+                        { "enum": ["juvenile", "adult"] },
+                        {
+                            "type": "object",
+                            "required": ["breeding"],
+                            "properties": { "breeding": { "type": "string" } }
+                        }
+                    ]
+                },
+                "arrival": date,
+                "habitat": { "type": "string" },
+                "health": {
+                    "type": "object",
+                    "properties": {
+                        "injured": { "type": "boolean" },
+                        "contagious": { "type": "boolean" },
+                        "medication": { "type": "string" },
+                        "vet_check": date
+                    }
+                }
+            }
+        }
+    })
+}
+
+/// `Catalogue` maps species to their `SpeciesInfo`.
+///
+/// Animals get the habitat of their species, unless their record names one.
+#[derive(Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(from = "BTreeMap<String, SpeciesInfo>")]
+pub struct Catalogue {
+    /// Canonical names to their description
+    species: BTreeMap<String, SpeciesInfo>,
+    /// Knows the canonical names of the species and their aliases
+    registry: Registry,
+}
+
+impl From<BTreeMap<String, SpeciesInfo>> for Catalogue {
+    fn from(species: BTreeMap<String, SpeciesInfo>) -> Self {
+        let mut registry = Registry::default();
+        for (name, info) in &species {
+            registry.add(name, &info.aliases);
+        }
+        Self { species, registry }
+    }
+}
+
+impl Catalogue {
+    pub fn is_empty(&self) -> bool {
+        self.species.is_empty()
+    }
+
+    /// The species of the catalogue and their aliases.
+    pub fn registry(&self) -> &Registry {
+        &self.registry
+    }
+
+    /// `info` looks up the description of the species `name`, which may also
+    /// be an alias.
+    pub fn info(&self, name: &str) -> Option<&SpeciesInfo> {
+        let canonical = self.registry.resolve(name)?;
+        self.species.get(canonical)
+    }
+
+    /// `relaxed` is the catalogue without the diet and strength of the species
+    /// `name`, so that `check` doesn't find conflicts for it.
+    pub(crate) fn relaxed(&self, name: &str) -> Self {
+        let mut catalogue = self.clone();
+        let canonical = self.registry.resolve(name);
+        if let Some(info) = canonical.and_then(|name| catalogue.species.get_mut(name)) {
+            info.carnivore = None;
+            info.strength = None;
+        }
+        catalogue
+    }
+
+    /// `complete` turns `records` into animals, taking omitted fields from
+    /// the catalogue.
+    ///
+    /// # Errors
+    ///
+    /// This function errors with `Error::MissingField` if a record omits a
+    /// field the catalogue doesn't know either.
+    pub fn complete(&self, records: Vec<Record>) -> Result<Vec<Animal>> {
+        records
+            .into_iter()
+            .map(|record| {
+                let info = self.info(&record.species);
+                let missing = |field| Error::MissingField {
+                    animal: record.name.clone(),
+                    species: record.species.clone(),
+                    field,
+                };
+
+                let carnivore = record
+                    .carnivore
+                    .or_else(|| info?.carnivore)
+                    .ok_or_else(|| missing("carnivore"))?;
+                let strength = record
+                    .strength
+                    .or_else(|| info?.default_strength(carnivore))
+                    .ok_or_else(|| missing("strength"))?;
+
+                Ok(Animal {
+                    name: record.name,
+                    species: record.species,
+                    strength,
+                    carnivore,
+                    sex: record.sex,
+                    breeding_status: record.breeding_status,
+                    arrival: record.arrival,
+                    habitat: record.habitat.or_else(|| info?.habitat.clone()),
+                    health: record.health,
+                })
+            })
+            .collect()
+    }
+
+    /// `check` finds the animals that contradict the catalogue.
+    pub fn check(&self, animals: &[Animal]) -> Vec<Conflict> {
+        let mut conflicts = vec![];
+        for animal in animals {
+            let info = match self.info(&animal.species) {
+                Some(info) => info,
+                None => continue,
+            };
+
+            if let Some(carnivore) = info.carnivore {
+                if animal.carnivore != carnivore {
+                    conflicts.push(Conflict::CatalogueDiet {
+                        animal: animal.clone(),
+                        carnivore,
+                    });
+                }
+            }
+            if let Some((min, max)) = info.strength {
+                if animal.strength < min || animal.strength > max {
+                    conflicts.push(Conflict::CatalogueStrength {
+                        animal: animal.clone(),
+                        min,
+                        max,
+                    });
+                }
+            }
+        }
+        conflicts
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn catalogue() -> Catalogue {
+        serde_json::from_str(
+            r#"{
+                "Lion": { "carnivore": true, "strength": [10, 20], "aliases": ["African Lion"] },
+                "Zebra": { "carnivore": false, "strength": [15, 25] },
+                "Parrot": {}
+            }"#,
+        )
+        .unwrap()
+    }
+
+    fn record(name: &str, species: &str, strength: Option<usize>) -> Record {
+        Record {
+            name: String::from(name),
+            species: String::from(species),
+            strength,
+            carnivore: None,
+            sex: None,
+            breeding_status: None,
+            arrival: None,
+            habitat: None,
+            health: None,
+        }
+    }
+
+    #[test]
+    fn complete_records() {
+        let catalogue = catalogue();
+        let animals = catalogue
+            .complete(vec![
+                record("Leo", "african lion", None),
+                record("Marty", "Zebra", Some(17)),
+            ])
+            .unwrap();
+
+        assert_eq!(animals[0].strength, 20);
+        assert!(animals[0].carnivore);
+        assert_eq!(animals[1].strength, 17);
+        assert!(!animals[1].carnivore);
+
+        assert!(matches!(
+            catalogue.complete(vec![record("Polly", "Parrot", Some(1))]),
+            Err(Error::MissingField {
+                field: "carnivore",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn schema() {
+        let schema = inventory_schema();
+        let properties = schema["items"]["properties"].as_object().unwrap();
+
+        let full = Record {
+            carnivore: Some(true),
+            sex: Some(Sex::Male),
+            breeding_status: Some(BreedingStatus::Adult),
+            arrival: NaiveDate::from_ymd_opt(2024, 5, 1),
+            habitat: Some(String::from("savanna")),
+            health: Some(Health::default()),
+            ..record("Leo", "Lion", Some(20))
+        };
+        let json = serde_json::to_value(&full).unwrap();
+        for field in json.as_object().unwrap().keys() {
+            assert!(properties.contains_key(field), "{} is missing", field);
+        }
+
+        // Omitted fields stay omitted
+        let json = serde_json::to_string(&record("Marty", "Zebra", None)).unwrap();
+        assert_eq!(json, r#"{"name":"Marty","species":"Zebra"}"#);
+    }
+
+    #[test]
+    fn contradicting_records() {
+        let catalogue = catalogue();
+        let mut animals = catalogue
+            .complete(vec![
+                record("Leo", "Lion", Some(21)),
+                record("Marty", "Zebra", None),
+            ])
+            .unwrap();
+        animals[1].carnivore = true;
+
+        assert_eq!(
+            catalogue.check(&animals),
+            [
+                Conflict::CatalogueStrength {
+                    animal: animals[0].clone(),
+                    min: 10,
+                    max: 20
+                },
+                Conflict::CatalogueDiet {
+                    animal: animals[1].clone(),
+                    carnivore: false
+                }
+            ]
+        );
+    }
+}
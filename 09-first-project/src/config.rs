@@ -0,0 +1,273 @@
+//! The configuration file of the zoo.
+//!
+//! Instead of passing the same options every time, they can be written to a
+//! `zoo.toml` file. Every key is named like its command line option and is
+//! optional. Tables in `overrides` apply to a single subcommand only:
+//!
+//! ```toml
+//! input = "animals.json"
+//! output-dir = "layouts"
+//! food = "Deer"
+//! catalogue = "catalogue.json"
+//! placement = "balanced"
+//! max-risk = 20.0
+//!
+//! [overrides.stats]
+//! growth = 10
+//! ```
+//!
+//! Relative paths are relative to the directory of the configuration file, so
+//! that it works from any working directory.
+//!
+//! An option given on the command line wins over the override of the
+//! subcommand, which wins over the top-level key, which wins over the default
+//! of the option.
+
+use crate::error::Error;
+use crate::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// The name of the configuration file `Config::discover` looks for.
+pub const FILE_NAME: &str = "zoo.toml";
+
+/// `Options` are the keys of the configuration file, or of one of its
+/// overrides.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Options {
+    /// The inventory, if no `INPUT` is given
+    pub input: Option<PathBuf>,
+    /// Where the solution files are written, instead of the working directory
+    pub output_dir: Option<PathBuf>,
+    pub food: Option<String>,
+    /// Write a feeding plan, see `FeedingPlan`
+    ///
+    /// [`FeedingPlan`]: crate::feeding::FeedingPlan
+    pub feeding: Option<bool>,
+    /// Comma separated keepers, who take turns feeding the cages
+    pub keepers: Option<String>,
+    pub roster: Option<PathBuf>,
+    pub catalogue: Option<PathBuf>,
+    /// The species registry, see `Registry`
+    ///
+    /// [`Registry`]: crate::species::Registry
+    pub species_file: Option<PathBuf>,
+    pub cost: Option<PathBuf>,
+    pub risk: Option<PathBuf>,
+    /// The placement strategy, see `Placement`
+    ///
+    /// [`Placement`]: crate::placement::Placement
+    pub placement: Option<String>,
+    pub diet_conflicts: Option<String>,
+    /// Days new arrivals stay in quarantine
+    pub quarantine: Option<i64>,
+    pub max_strength_ratio: Option<f64>,
+    pub max_risk: Option<f64>,
+    /// How much a predator may grow before a cage is at risk
+    pub growth: Option<usize>,
+}
+
+/// `Config` is a parsed configuration file.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Config {
+    /// The top-level keys, for all subcommands
+    pub options: Options,
+    /// The keys for single subcommands
+    pub overrides: BTreeMap<String, Options>,
+}
+
+impl Options {
+    /// The keys of the configuration file, in the order of the fields.
+    pub const KEYS: &'static [&'static str] = &[
+        "input",
+        "output-dir",
+        "food",
+        "feeding",
+        "keepers",
+        "roster",
+        "catalogue",
+        "species-file",
+        "cost",
+        "risk",
+        "placement",
+        "diet-conflicts",
+        "quarantine",
+        "max-strength-ratio",
+        "max-risk",
+        "growth",
+    ];
+
+    /// `values` lists the keys that are set, with their values written like
+    /// on the command line.
+    pub fn values(&self) -> BTreeMap<String, String> {
+        let table = match toml::Value::try_from(self) {
+            Ok(toml::Value::Table(table)) => table,
+            _ => unreachable!("options serialize to a table"),
+        };
+        table
+            .into_iter()
+            .map(|(key, value)| {
+                let value = match value {
+                    toml::Value::String(value) => value,
+                    value => value.to_string(),
+                };
+                (key, value)
+            })
+            .collect()
+    }
+
+    /// Makes the relative paths relative to `dir` instead.
+    fn resolve(&mut self, dir: &Path) {
+        let mut paths = [
+            &mut self.input,
+            &mut self.output_dir,
+            &mut self.roster,
+            &mut self.catalogue,
+            &mut self.species_file,
+            &mut self.cost,
+            &mut self.risk,
+        ];
+        for path in paths.iter_mut().filter_map(|path| path.as_mut()) {
+            *path = dir.join(&*path);
+        }
+    }
+}
+
+impl Config {
+    /// `load` reads the configuration file at `path`.
+    ///
+    /// # Errors
+    ///
+    /// This function errors with `Error::Config`, if the file can't be read
+    /// or has unknown keys or values of the wrong type.
+    pub fn load(path: &Path) -> Result<Self> {
+        let error = |message: String| Error::Config {
+            path: path.to_path_buf(),
+            message,
+        };
+        let mut config: Self = fs::read_to_string(path)
+            .map_err(|err| error(err.to_string()))?
+            .parse()
+            .map_err(|err: Error| error(err.to_string()))?;
+
+        let dir = path.parent().unwrap_or_else(|| Path::new(""));
+        config.options.resolve(dir);
+        for options in config.overrides.values_mut() {
+            options.resolve(dir);
+        }
+        Ok(config)
+    }
+
+    /// `discover` loads the `zoo.toml` in `dir`, if there is one.
+    pub fn discover(dir: &Path) -> Result<Option<(PathBuf, Self)>> {
+        let path = dir.join(FILE_NAME);
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let config = Self::load(&path)?;
+        Ok(Some((path, config)))
+    }
+
+    /// `values` are the keys that are set for the subcommand `command`, with
+    /// its overrides applied.
+    pub fn values(&self, command: &str) -> BTreeMap<String, String> {
+        let mut values = self.options.values();
+        if let Some(overrides) = self.overrides.get(command) {
+            values.extend(overrides.values());
+        }
+        values
+    }
+}
+
+impl FromStr for Config {
+    type Err = Error;
+
+    /// Parses a configuration, without resolving relative paths.
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = |err: toml::de::Error| Error::InvalidValue(err.to_string());
+        let mut table: toml::value::Table = toml::from_str(s).map_err(invalid)?;
+
+        // `Options` denies unknown keys, so `overrides` is taken out first
+        let overrides = match table.remove("overrides") {
+            Some(overrides) => overrides.try_into().map_err(invalid)?,
+            None => BTreeMap::new(),
+        };
+        let options = toml::Value::Table(table).try_into().map_err(invalid)?;
+        Ok(Self { options, overrides })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const CONFIG: &str = r#"
+        input = "animals.json"
+        food = "Deer"
+        feeding = true
+        max-risk = 20.0
+
+        [overrides.stats]
+        food = "Zebra"
+        growth = 10
+    "#;
+
+    #[test]
+    fn precedence() {
+        let config: Config = CONFIG.parse().unwrap();
+
+        let cage = config.values("cage");
+        assert_eq!(cage["food"], "Deer");
+        assert_eq!(cage["feeding"], "true");
+        assert_eq!(cage["max-risk"], "20.0");
+        assert!(!cage.contains_key("growth"));
+
+        let stats = config.values("stats");
+        assert_eq!(stats["food"], "Zebra");
+        assert_eq!(stats["growth"], "10");
+        assert_eq!(stats["input"], "animals.json");
+    }
+
+    #[test]
+    fn invalid() {
+        assert!("fod = \"Deer\"".parse::<Config>().is_err());
+        assert!("max-risk = \"high\"".parse::<Config>().is_err());
+        assert!("[overrides.stats]\ngrowth = -1".parse::<Config>().is_err());
+
+        let options: Options = toml::from_str(
+            r#"
+                input = "a.json"
+                output-dir = "out"
+                food = "Deer"
+                feeding = true
+                keepers = "Alice, Bob"
+                roster = "roster.json"
+                catalogue = "catalogue.json"
+                species-file = "species.json"
+                cost = "cost.json"
+                risk = "risk.json"
+                placement = "weakest"
+                diet-conflicts = "majority"
+                quarantine = 10
+                max-strength-ratio = 3.0
+                max-risk = 20.0
+                growth = 5
+            "#,
+        )
+        .unwrap();
+        let mut keys: Vec<_> = Options::KEYS.to_vec();
+        keys.sort_unstable();
+        assert_eq!(options.values().keys().collect::<Vec<_>>(), keys);
+
+        let mut config: Config = CONFIG.parse().unwrap();
+        config.options.resolve(Path::new("zoo"));
+        assert_eq!(
+            config.options.input,
+            Some(PathBuf::from("zoo/animals.json"))
+        );
+    }
+}
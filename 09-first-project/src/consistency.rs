@@ -0,0 +1,281 @@
+//! Species-level consistency checks, run before caging.
+//!
+//! `cage_em_all` splits the animals by their `carnivore` flag before grouping
+//! them by species. If the records of a species disagree on the diet, the
+//! species ends up in two cages, which breaks rule 2. Such conflicts are
+//! detected here and either reported or resolved.
+
+use crate::error::Error;
+use crate::species::{normalize, same_species};
+use crate::{Animal, Result};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+/// `Conflict` is an inconsistency between the records of a species.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub enum Conflict {
+    /// Some animals of the species are marked as carnivores, others not
+    Diet {
+        species: String,
+        carnivores: Vec<String>,
+        herbivores: Vec<String>,
+    },
+    /// The strongest animal of the species is more than the allowed factor
+    /// stronger than the weakest one
+    Strength {
+        species: String,
+        weakest: Animal,
+        strongest: Animal,
+    },
+    /// An animal has a different diet than its species in the `Catalogue`
+    ///
+    /// [`Catalogue`]: crate::catalogue::Catalogue
+    CatalogueDiet { animal: Animal, carnivore: bool },
+    /// An animal is weaker or stronger than its species in the `Catalogue`
+    /// usually is
+    ///
+    /// [`Catalogue`]: crate::catalogue::Catalogue
+    CatalogueStrength {
+        animal: Animal,
+        min: usize,
+        max: usize,
+    },
+}
+
+/// `DietPolicy` decides what happens to species with a `Conflict::Diet`.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DietPolicy {
+    /// Don't cage the animals and report the conflicts
+    Error,
+    /// Treat the whole species as carnivores. This is the safe choice, since
+    /// a carnivore never ends up with prey.
+    Carnivore,
+    /// Use the diet of the majority of the records. Ties count as carnivores.
+    Majority,
+}
+
+/// `Consistency` configures the consistency checks.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Consistency {
+    pub diet: DietPolicy,
+    /// How many times stronger than the weakest animal of a species the
+    /// strongest one may be. Exceeding this is always an error, since there is
+    /// no sensible way to guess the correct strength. `None` disables the
+    /// check.
+    pub max_strength_ratio: Option<f64>,
+}
+
+impl Default for Consistency {
+    fn default() -> Self {
+        Self {
+            diet: DietPolicy::Error,
+            max_strength_ratio: None,
+        }
+    }
+}
+
+impl Consistency {
+    /// `check` finds all conflicts in `animals`.
+    pub fn check(&self, animals: &[Animal]) -> Vec<Conflict> {
+        let mut species: BTreeMap<String, Vec<&Animal>> = BTreeMap::new();
+        for animal in animals {
+            species
+                .entry(normalize(&animal.species))
+                .or_default()
+                .push(animal);
+        }
+
+        let mut conflicts = vec![];
+        for animals in species.values() {
+            let species = &animals[0].species;
+            let names = |carnivore: bool| {
+                animals
+                    .iter()
+                    .filter(|animal| animal.carnivore == carnivore)
+                    .map(|animal| animal.name.clone())
+                    .collect::<Vec<_>>()
+            };
+            let (carnivores, herbivores) = (names(true), names(false));
+            if !carnivores.is_empty() && !herbivores.is_empty() {
+                conflicts.push(Conflict::Diet {
+                    species: species.to_string(),
+                    carnivores,
+                    herbivores,
+                });
+            }
+
+            if let Some(max_ratio) = self.max_strength_ratio {
+                let weakest = animals.iter().min().expect("species has an animal");
+                let strongest = animals.iter().max().expect("species has an animal");
+                // A strength of 0 would make every other strength infinitely
+                // stronger
+                let ratio = strongest.strength as f64 / weakest.strength.max(1) as f64;
+                if ratio > max_ratio {
+                    conflicts.push(Conflict::Strength {
+                        species: species.to_string(),
+                        weakest: (*weakest).clone(),
+                        strongest: (*strongest).clone(),
+                    });
+                }
+            }
+        }
+        conflicts
+    }
+
+    /// `resolve` resolves the conflicts in `animals` according to the
+    /// policies and returns the resolved conflicts.
+    ///
+    /// # Errors
+    ///
+    /// This function errors with `Error::Inconsistent` if there is a conflict
+    /// that can't be resolved.
+    pub fn resolve(&self, animals: &mut [Animal]) -> Result<Vec<Conflict>> {
+        let conflicts = self.check(animals);
+        let (resolvable, unresolvable): (Vec<_>, Vec<_>) =
+            conflicts.into_iter().partition(|conflict| match conflict {
+                Conflict::Diet { .. } => self.diet != DietPolicy::Error,
+                _ => false,
+            });
+        if !unresolvable.is_empty() {
+            return Err(Error::Inconsistent(unresolvable));
+        }
+
+        for conflict in &resolvable {
+            if let Conflict::Diet {
+                species,
+                carnivores,
+                herbivores,
+            } = conflict
+            {
+                let carnivore = match self.diet {
+                    DietPolicy::Majority => carnivores.len() >= herbivores.len(),
+                    _ => true,
+                };
+                animals
+                    .iter_mut()
+                    .filter(|animal| same_species(&animal.species, species))
+                    .for_each(|animal| animal.carnivore = carnivore);
+            }
+        }
+        Ok(resolvable)
+    }
+}
+
+impl FromStr for DietPolicy {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "error" => Ok(Self::Error),
+            "carnivore" => Ok(Self::Carnivore),
+            "majority" => Ok(Self::Majority),
+            _ => Err(Error::InvalidValue(format!(
+                "invalid value `{}`, expected `error`, `carnivore` or `majority`",
+                s
+            ))),
+        }
+    }
+}
+
+fn diet(carnivore: bool) -> &'static str {
+    if carnivore {
+        "a carnivore"
+    } else {
+        "a herbivore"
+    }
+}
+
+impl Display for Conflict {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Diet {
+                species,
+                carnivores,
+                herbivores,
+            } => write!(
+                f,
+                "species {} has carnivores ({}) and herbivores ({})",
+                species,
+                carnivores.join(", "),
+                herbivores.join(", ")
+            ),
+            Self::Strength {
+                species,
+                weakest,
+                strongest,
+            } => write!(
+                f,
+                "strengths of species {} range from {} ({}) to {} ({})",
+                species, weakest.strength, weakest.name, strongest.strength, strongest.name
+            ),
+            Self::CatalogueDiet { animal, carnivore } => write!(
+                f,
+                "{} the {} is {}, but the catalogue lists the species as {}",
+                animal.name,
+                animal.species,
+                diet(animal.carnivore),
+                diet(*carnivore)
+            ),
+            Self::CatalogueStrength { animal, min, max } => write!(
+                f,
+                "{} the {} has strength {}, but the species ranges from {} to {}",
+                animal.name, animal.species, animal.strength, min, max
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::animal::animal;
+
+    #[test]
+    fn diet_conflicts() {
+        let animals = vec![
+            animal("Sam", "Dog", 12, true),
+            animal("Chappy", "Dog", 10, false),
+            animal("Rex", "Dog", 11, false),
+        ];
+
+        let mut strict = animals.clone();
+        assert!(Consistency::default().resolve(&mut strict).is_err());
+
+        let mut majority = animals.clone();
+        let consistency = Consistency {
+            diet: DietPolicy::Majority,
+            ..Consistency::default()
+        };
+        assert_eq!(consistency.resolve(&mut majority).unwrap().len(), 1);
+        assert!(majority.iter().all(|animal| !animal.carnivore));
+
+        let mut carnivores = animals;
+        let consistency = Consistency {
+            diet: DietPolicy::Carnivore,
+            ..Consistency::default()
+        };
+        assert_eq!(consistency.resolve(&mut carnivores).unwrap().len(), 1);
+        assert!(carnivores.iter().all(|animal| animal.carnivore));
+    }
+
+    #[test]
+    fn strength_conflicts() {
+        let mut animals = vec![
+            animal("Sam", "Dog", 12, true),
+            animal("Chappy", "Dog", 0, true),
+        ];
+        let consistency = Consistency {
+            max_strength_ratio: Some(12.0),
+            ..Consistency::default()
+        };
+        assert!(consistency.check(&animals).is_empty());
+
+        animals.push(animal("Rex", "Dog", 13, true));
+        assert!(matches!(
+            consistency.resolve(&mut animals),
+            Err(Error::Inconsistent(conflicts)) if conflicts.len() == 1
+        ));
+    }
+}
@@ -0,0 +1,278 @@
+//! Cage layouts that are as cheap as possible.
+//!
+//! `cage_em_all` uses as few cages as it can, by moving herbivores in with
+//! every predator they are too strong for. But a mixed cage may well cost
+//! more than a carnivore cage and a herbivore cage together. `optimise` lays
+//! out the cages by their total cost instead.
+//!
+//! The cost model is a JSON object, where every field is optional:
+//!
+//! ```json
+//! {
+//!     "cage": { "Carnivores": 1000, "Mixed": 2500, "Herbivores": 800 },
+//!     "animal": 50,
+//!     "habitat": { "arctic": 2000 }
+//! }
+//! ```
+
+use crate::placement::Placement;
+use crate::species::same_species;
+use crate::{group_animals, relocate_animals, Animal, Cage, CageKind, Result};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::fmt::{self, Display, Formatter};
+
+/// How many carnivore cages `optimise` considers for herbivores at most.
+/// Every combination of them is tried, so this bounds the work to
+/// `2^MAX_CANDIDATES` layouts.
+pub const MAX_CANDIDATES: usize = 12;
+
+/// `CostModel` describes what building and running a cage costs.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct CostModel {
+    /// Cost of a cage by its kind, kinds that aren't listed are free
+    pub cage: BTreeMap<CageKind, f64>,
+    /// Cost of every animal in a cage
+    pub animal: f64,
+    /// Additional cost of a cage providing the habitat
+    pub habitat: BTreeMap<String, f64>,
+}
+
+/// `CageCost` is the cost of a single cage.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct CageCost {
+    /// The position of the cage in the layout, starting at 1
+    pub cage: usize,
+    pub kind: CageKind,
+    pub habitat: Option<String>,
+    /// Cost of the cage itself
+    pub base: f64,
+    /// Cost of the animals in the cage
+    pub animals: f64,
+    /// Cost of providing the habitat
+    pub habitat_cost: f64,
+    pub total: f64,
+}
+
+/// `CostBreakdown` is the cost of a layout, cage by cage.
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+pub struct CostBreakdown {
+    pub cages: Vec<CageCost>,
+    pub total: f64,
+}
+
+impl CostModel {
+    /// `habitat_cost` is the additional cost of a cage providing `habitat`.
+    pub fn habitat_cost(&self, habitat: &str) -> f64 {
+        self.habitat
+            .iter()
+            .find(|(name, _)| same_species(name, habitat))
+            .map(|(_, cost)| *cost)
+            .unwrap_or(0.0)
+    }
+
+    /// `cage_cost` is the cost of `cage`, at position `index` of a layout.
+    pub fn cage_cost(&self, index: usize, cage: &Cage) -> CageCost {
+        let kind = cage.kind();
+        let habitat = cage.habitat().map(str::to_string);
+        let base = self.cage.get(&kind).copied().unwrap_or(0.0);
+        let animals = self.animal * cage.animals.len() as f64;
+        let habitat_cost = habitat.as_deref().map_or(0.0, |h| self.habitat_cost(h));
+        CageCost {
+            cage: index + 1,
+            kind,
+            habitat,
+            base,
+            animals,
+            habitat_cost,
+            total: base + animals + habitat_cost,
+        }
+    }
+
+    /// `breakdown` computes the cost of every cage in `cages`.
+    pub fn breakdown(&self, cages: &[Cage]) -> CostBreakdown {
+        let cages: Vec<_> = cages
+            .iter()
+            .enumerate()
+            .map(|(i, cage)| self.cage_cost(i, cage))
+            .collect();
+        let total = cages.iter().map(|cage| cage.total).sum();
+        CostBreakdown { cages, total }
+    }
+
+    fn total(&self, cages: &[Cage]) -> f64 {
+        cages
+            .iter()
+            .enumerate()
+            .map(|(i, cage)| self.cage_cost(i, cage).total)
+            .sum()
+    }
+}
+
+/// `optimise` puts the `animals` into cages like `cage_em_all_with`, but picks the
+/// layout with the lowest total cost under `model`, rather than the one with
+/// the fewest cages.
+///
+/// Carnivores are grouped exactly like in `cage_em_all`. What changes is
+/// which carnivore cages take in herbivores: every combination of carnivore
+/// cages is tried, and herbivores that are too weak for all the chosen cages
+/// get cages of their own. Ties go to the layout with fewer cages. With more
+/// than `MAX_CANDIDATES` carnivore cages, the weaker ones are always open to
+/// herbivores.
+///
+/// Layouts `accept` rejects, e.g. because they are too risky, are only used
+/// if it rejects all of them.
+///
+/// # Errors
+///
+/// This function errors in the same cases as `cage_em_all`.
+pub fn optimise(
+    model: &CostModel,
+    placement: &Placement,
+    animals: Vec<Animal>,
+    food: Option<&str>,
+    accept: impl Fn(&[Cage]) -> bool,
+) -> Result<Vec<Cage>> {
+    let (carnivore_cages, herbivore_groups) = group_animals(animals, food)?;
+
+    // A cage that already holds the food is mixed anyway. The carnivore cages
+    // are sorted strongest first, so the weakest ones are the last ones.
+    let candidates: Vec<usize> = (0..carnivore_cages.len())
+        .filter(|&i| carnivore_cages[i].kind() == CageKind::Carnivores)
+        .collect();
+    let fixed = candidates.len().saturating_sub(MAX_CANDIDATES);
+    let (always, candidates) = candidates.split_at(fixed);
+
+    let mut best: Option<(bool, f64, Vec<Cage>)> = None;
+    for mask in 0..1u32 << candidates.len() {
+        let mut open: Vec<bool> = carnivore_cages
+            .iter()
+            .map(|cage| cage.kind() != CageKind::Carnivores)
+            .collect();
+        for &i in always {
+            open[i] = true;
+        }
+        for (bit, &i) in candidates.iter().enumerate() {
+            open[i] = mask & (1 << bit) != 0;
+        }
+
+        let cages = relocate_animals(
+            carnivore_cages.clone(),
+            herbivore_groups.clone(),
+            placement,
+            |i| open[i],
+        );
+        let accepted = accept(&cages);
+        let cost = model.total(&cages);
+        let better = match &best {
+            None => true,
+            Some((best_accepted, best_cost, best_cages)) => {
+                (accepted, Reverse(cost), Reverse(cages.len()))
+                    > (
+                        *best_accepted,
+                        Reverse(*best_cost),
+                        Reverse(best_cages.len()),
+                    )
+            }
+        };
+        if better {
+            best = Some((accepted, cost, cages));
+        }
+    }
+
+    Ok(best.map(|(_, _, cages)| cages).unwrap_or_default())
+}
+
+impl Display for CostBreakdown {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Cost: {:.2}", self.total)?;
+        for cage in &self.cages {
+            write!(
+                f,
+                "  cage {:>3}: {:<10} {:>10.2} (cage {:.2}, animals {:.2}",
+                cage.cage, cage.kind, cage.total, cage.base, cage.animals
+            )?;
+            if let Some(habitat) = &cage.habitat {
+                write!(f, ", {} {:.2}", habitat, cage.habitat_cost)?;
+            }
+            writeln!(f, ")")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::animal::animal;
+
+    fn animals() -> Vec<Animal> {
+        vec![
+            animal("Leo", "Lion", 10, true),
+            animal("Marty", "Zebra", 20, false),
+            animal("Gloria", "Hippo", 30, false),
+        ]
+    }
+
+    fn model(carnivores: f64, mixed: f64, herbivores: f64) -> CostModel {
+        CostModel {
+            cage: vec![
+                (CageKind::Carnivores, carnivores),
+                (CageKind::Mixed, mixed),
+                (CageKind::Herbivores, herbivores),
+            ]
+            .into_iter()
+            .collect(),
+            ..CostModel::default()
+        }
+    }
+
+    #[test]
+    fn cheap_mixed_cages() {
+        let cages = optimise(
+            &model(10.0, 15.0, 10.0),
+            &Placement::default(),
+            animals(),
+            None,
+            |_| true,
+        )
+        .unwrap();
+
+        assert_eq!(cages.len(), 1);
+        assert_eq!(cages[0].kind(), CageKind::Mixed);
+    }
+
+    #[test]
+    fn expensive_mixed_cages() {
+        let model = model(10.0, 25.0, 10.0);
+        let cages = optimise(&model, &Placement::default(), animals(), None, |_| true).unwrap();
+
+        assert_eq!(cages.len(), 2);
+        assert_eq!(cages[0].kind(), CageKind::Carnivores);
+        assert_eq!(cages[1].kind(), CageKind::Herbivores);
+        assert_eq!(model.breakdown(&cages).total, 20.0);
+    }
+
+    #[test]
+    fn cost_breakdown() {
+        let mut polar = animal("Nanook", "Polar Bear", 40, true);
+        polar.habitat = Some(String::from("Arctic"));
+        let cages = [
+            Cage::from(vec![polar]),
+            Cage::from(vec![animal("Marty", "Zebra", 20, false)]),
+        ];
+        let model = CostModel {
+            animal: 2.0,
+            habitat: vec![(String::from("arctic"), 100.0)].into_iter().collect(),
+            ..model(10.0, 15.0, 5.0)
+        };
+
+        let breakdown = model.breakdown(&cages);
+
+        assert_eq!(breakdown.cages[0].total, 112.0);
+        assert_eq!(breakdown.cages[1].total, 7.0);
+        assert_eq!(breakdown.total, 119.0);
+    }
+}
@@ -0,0 +1,392 @@
+//! Explanations for inventories that can't be caged.
+//!
+//! When caging fails, the error only names the first problem it ran into.
+//! `diagnose` narrows the inventory down to a minimal set of animals that
+//! still can't be caged (an unsatisfiable core): removing any single animal of
+//! it makes the problem go away. It then tries single changes to the whole
+//! inventory and to the configuration of the `Caging`, and suggests the ones
+//! that make caging possible.
+
+use crate::caging::Caging;
+use crate::consistency::{Conflict, Consistency, DietPolicy};
+use crate::error::Error;
+use crate::species::{normalize, same_species};
+use crate::{Animal, Cage, CageKind, Result};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::{self, Display, Formatter};
+use std::mem::discriminant;
+
+/// `Diagnosis` explains why an inventory can't be caged.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct Diagnosis {
+    /// Why the animals of the `core` can't be caged
+    pub explanation: String,
+    /// A minimal set of animals that can't be caged together, in the order of
+    /// the inventory
+    pub core: Vec<Animal>,
+    /// Single changes that make the whole inventory feasible
+    pub suggestions: Vec<Suggestion>,
+}
+
+/// `Suggestion` is a single change that makes an inventory feasible.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub enum Suggestion {
+    /// Remove the animal from the inventory
+    Remove(Animal),
+    /// Don't feed the food species to anyone, but give it a cage of its own
+    KeepFood(String),
+    /// Resolve diet conflicts with the policy, instead of reporting them
+    Diet(DietPolicy),
+    /// Give the herbivores of the species a cage of their own, instead of
+    /// putting them in with a predator
+    AddCage(String),
+    /// Relax a limit of the `Caging`
+    Relax(Override),
+}
+
+/// `Override` is a limit of the `Caging` that a `Suggestion` relaxes.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub enum Override {
+    /// Accept layouts up to this risk
+    MaxRisk(f64),
+    /// Don't check the diet and strength of the species against the
+    /// catalogue
+    Catalogue(String),
+}
+
+/// `diagnose` explains why `caging` can't cage `animals`, or returns `None`
+/// if it can.
+pub fn diagnose(caging: &Caging, animals: &[Animal], food: Option<&str>) -> Option<Diagnosis> {
+    // Costs don't change whether caging is possible, but make it a lot slower.
+    // Only the optimiser may find a layout that is safe enough, though.
+    let caging = Caging {
+        cost: caging.cost.clone().filter(|_| caging.max_risk.is_some()),
+        ..caging.clone()
+    };
+    let solve = |animals: &[Animal], food: Option<&str>| caging.cage(animals.to_vec(), food);
+
+    let error = solve(animals, food).err()?;
+    let same_problem = |animals: &[Animal]| match solve(animals, food) {
+        Err(err) => discriminant(&err) == discriminant(&error),
+        Ok(_) => false,
+    };
+
+    // Drop every animal the problem doesn't depend on. What remains is
+    // minimal, since removing any animal of it was tried.
+    let mut core = animals.to_vec();
+    let mut i = 0;
+    while i < core.len() {
+        let mut without = core.clone();
+        without.remove(i);
+        if same_problem(&without) {
+            core = without;
+        } else {
+            i += 1;
+        }
+    }
+    let explanation = match solve(&core, food) {
+        Err(err) => explain(&err),
+        Ok(_) => unreachable!("the core can't be caged"),
+    };
+
+    let mut suggestions = vec![];
+    for animal in &core {
+        // Animals only compare their species, so look for the exact record
+        let mut without = animals.to_vec();
+        let position = animals.iter().position(|a| same_animal(a, animal));
+        without.remove(position.expect("the core is part of the inventory"));
+        if solve(&without, food).is_ok() {
+            suggestions.push(Suggestion::Remove(animal.clone()));
+        }
+    }
+    if let Some(food) = food {
+        if solve(animals, None).is_ok() {
+            // Spell the species like the inventory does
+            let species = animals
+                .iter()
+                .find(|animal| same_species(&animal.species, food))
+                .map_or(food, |animal| animal.species.as_str());
+            suggestions.push(Suggestion::KeepFood(species.to_string()));
+        }
+    }
+    if let Error::Inconsistent(conflicts) = &error {
+        for diet in [DietPolicy::Carnivore, DietPolicy::Majority] {
+            if resolved(animals, diet).is_ok_and(|animals| solve(&animals, food).is_ok()) {
+                suggestions.push(Suggestion::Diet(diet));
+            }
+        }
+        for species in catalogued(conflicts) {
+            let relaxed = Caging {
+                catalogue: caging.catalogue.relaxed(&species),
+                ..caging.clone()
+            };
+            if relaxed.cage(animals.to_vec(), food).is_ok() {
+                suggestions.push(Suggestion::Relax(Override::Catalogue(species)));
+            }
+        }
+    }
+    if let Error::TooRisky { max_risk, .. } = error {
+        let unlimited = Caging {
+            max_risk: None,
+            ..caging.clone()
+        };
+        if let Ok(layout) = unlimited.cage(animals.to_vec(), food) {
+            for species in herbivores_with_predators(&layout.cages, food) {
+                let cages = separated(&layout.cages, &species);
+                if caging.risk.assess(&caging, &cages, food).total <= max_risk {
+                    suggestions.push(Suggestion::AddCage(species));
+                }
+            }
+            suggestions.push(Suggestion::Relax(Override::MaxRisk(layout.risk.total)));
+        }
+    }
+
+    Some(Diagnosis {
+        explanation,
+        core,
+        suggestions,
+    })
+}
+
+fn same_animal(a: &Animal, b: &Animal) -> bool {
+    a.name == b.name && a.species == b.species && a.strength == b.strength
+}
+
+/// The species that contradict the catalogue, spelled like the inventory does.
+fn catalogued(conflicts: &[Conflict]) -> Vec<String> {
+    let mut species = BTreeMap::new();
+    for conflict in conflicts {
+        if let Conflict::CatalogueDiet { animal, .. } | Conflict::CatalogueStrength { animal, .. } =
+            conflict
+        {
+            species
+                .entry(normalize(&animal.species))
+                .or_insert_with(|| animal.species.clone());
+        }
+    }
+    species.into_values().collect()
+}
+
+/// The herbivore species that share a cage with a predator, in the order of
+/// the cages. The `food` is supposed to be there.
+fn herbivores_with_predators(cages: &[Cage], food: Option<&str>) -> Vec<String> {
+    let mut species: Vec<String> = vec![];
+    let is_food = |animal: &Animal| food.is_some_and(|food| same_species(&animal.species, food));
+    for cage in cages.iter().filter(|cage| cage.kind() == CageKind::Mixed) {
+        for animal in cage.animals.iter().filter(|a| !a.carnivore && !is_food(a)) {
+            if !species.iter().any(|s| same_species(s, &animal.species)) {
+                species.push(animal.species.clone());
+            }
+        }
+    }
+    species
+}
+
+/// The `cages`, with the herbivores of the `species` moved out of the mixed
+/// cages into a cage of their own.
+fn separated(cages: &[Cage], species: &str) -> Vec<Cage> {
+    let mut cages = cages.to_vec();
+    let mut moved = vec![];
+    for cage in cages
+        .iter_mut()
+        .filter(|cage| cage.kind() == CageKind::Mixed)
+    {
+        let (leaving, staying): (Vec<_>, Vec<_>) = cage
+            .animals
+            .drain(..)
+            .partition(|animal| !animal.carnivore && same_species(&animal.species, species));
+        cage.animals = staying;
+        moved.extend(leaving);
+    }
+    cages.push(Cage::from(moved));
+    cages
+}
+
+fn resolved(animals: &[Animal], diet: DietPolicy) -> Result<Vec<Animal>> {
+    let consistency = Consistency {
+        diet,
+        ..Consistency::default()
+    };
+    let mut animals = animals.to_vec();
+    consistency.resolve(&mut animals)?;
+    Ok(animals)
+}
+
+fn explain(error: &Error) -> String {
+    match error {
+        Error::FoodTooStrong { food, predator } => format!(
+            "food species {} can't go with {}, because {} ({}) >= weakest predator {} ({})",
+            food.species,
+            predator.species,
+            food.name,
+            food.strength,
+            predator.name,
+            predator.strength
+        ),
+        _ => error.to_string(),
+    }
+}
+
+impl Display for Suggestion {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Remove(animal) => write!(f, "remove {} the {}", animal.name, animal.species),
+            Self::KeepFood(food) => write!(
+                f,
+                "give the {} a cage of its own instead of feeding it",
+                food
+            ),
+            Self::Diet(DietPolicy::Carnivore) => {
+                write!(f, "treat species with conflicting diets as carnivores")
+            }
+            Self::Diet(DietPolicy::Majority) => {
+                write!(f, "give species with conflicting diets their majority diet")
+            }
+            Self::Diet(DietPolicy::Error) => write!(f, "report diet conflicts"),
+            Self::AddCage(species) => write!(f, "give the {} a cage of its own", species),
+            Self::Relax(Override::MaxRisk(risk)) => write!(f, "allow a risk of {:.2}", risk),
+            Self::Relax(Override::Catalogue(species)) => write!(
+                f,
+                "don't check the diet and strength of the {} against the catalogue",
+                species
+            ),
+        }
+    }
+}
+
+impl Display for Diagnosis {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.explanation)?;
+        writeln!(f, "These animals can't be caged together:")?;
+        for animal in &self.core {
+            writeln!(
+                f,
+                "  - {} the {} ({})",
+                animal.name, animal.species, animal.strength
+            )?;
+        }
+        if self.suggestions.is_empty() {
+            writeln!(f, "No single change makes caging possible.")?;
+        } else {
+            writeln!(f, "Any of these changes makes caging possible:")?;
+            for suggestion in &self.suggestions {
+                writeln!(f, "  - {}", suggestion)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::animal::animal;
+
+    #[test]
+    fn food_too_strong() {
+        let animals = [
+            animal("Rexy", "T-Rex", 10, true),
+            animal("Tiny", "T-Rex", 4, true),
+            animal("Molly", "Cat", 5, false),
+            animal("Tom", "Cat", 2, false),
+            animal("Marty", "Zebra", 20, false),
+        ];
+
+        let diagnosis = diagnose(&Caging::default(), &animals, Some("Cat")).unwrap();
+
+        assert_eq!(diagnosis.core, [animals[1].clone(), animals[2].clone()]);
+        assert_eq!(
+            diagnosis.explanation,
+            "food species Cat can't go with T-Rex, because Molly (5) >= weakest predator Tiny (4)"
+        );
+        assert_eq!(
+            diagnosis.suggestions,
+            [
+                Suggestion::Remove(animals[1].clone()),
+                Suggestion::Remove(animals[2].clone()),
+                Suggestion::KeepFood(String::from("Cat")),
+            ]
+        );
+
+        assert!(diagnose(&Caging::default(), &animals, None).is_none());
+    }
+
+    #[test]
+    fn diet_conflict() {
+        let animals = [
+            animal("Sam", "Dog", 12, true),
+            animal("Chappy", "Dog", 10, false),
+            animal("Rex", "Dog", 11, false),
+            animal("Marty", "Zebra", 20, false),
+        ];
+
+        let diagnosis = diagnose(&Caging::default(), &animals, None).unwrap();
+
+        assert_eq!(diagnosis.core, [animals[0].clone(), animals[2].clone()]);
+        assert_eq!(
+            diagnosis.suggestions,
+            [
+                Suggestion::Remove(animals[0].clone()),
+                Suggestion::Diet(DietPolicy::Carnivore),
+                Suggestion::Diet(DietPolicy::Majority),
+            ]
+        );
+    }
+
+    #[test]
+    fn catalogue_conflict() {
+        let caging = Caging {
+            catalogue: serde_json::from_str(r#"{ "Lion": { "strength": [10, 20] } }"#).unwrap(),
+            ..Caging::default()
+        };
+        let animals = [
+            animal("Leo", "Lion", 15, true),
+            animal("Simba", "lion", 2, true),
+            animal("Marty", "Zebra", 20, false),
+        ];
+
+        let diagnosis = diagnose(&caging, &animals, None).unwrap();
+
+        assert_eq!(diagnosis.core, [animals[1].clone()]);
+        assert_eq!(
+            diagnosis.explanation,
+            "the inventory is inconsistent:\n  - Simba the lion has strength 2, but the species ranges from 10 to 20"
+        );
+        assert_eq!(
+            diagnosis.suggestions,
+            [
+                Suggestion::Remove(animals[1].clone()),
+                Suggestion::Relax(Override::Catalogue(String::from("lion"))),
+            ]
+        );
+    }
+
+    #[test]
+    fn too_risky() {
+        let caging = Caging {
+            max_risk: Some(5.0),
+            ..Caging::default()
+        };
+        let animals = [
+            animal("Leo", "Lion", 20, true),
+            animal("Marty", "Zebra", 21, false),
+        ];
+
+        let diagnosis = diagnose(&caging, &animals, None).unwrap();
+
+        assert_eq!(
+            diagnosis.suggestions,
+            [
+                Suggestion::Remove(animals[0].clone()),
+                Suggestion::Remove(animals[1].clone()),
+                Suggestion::AddCage(String::from("Zebra")),
+                Suggestion::Relax(Override::MaxRisk(11.0)),
+            ]
+        );
+        assert_eq!(
+            diagnosis.suggestions[3].to_string(),
+            "allow a risk of 11.00"
+        );
+    }
+}
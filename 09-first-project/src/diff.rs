@@ -0,0 +1,336 @@
+//! Differences between two cage layouts.
+
+use crate::species::normalize;
+use crate::{Animal, Cage, Result};
+use serde::Serialize;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fmt::{self, Display, Formatter};
+
+/// `Change` describes what happened to a single animal between two layouts.
+///
+/// Cages are identified by their position in the layout, starting at 1.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub enum Change {
+    Added {
+        animal: Animal,
+        to: usize,
+    },
+    Removed {
+        animal: Animal,
+        from: usize,
+    },
+    Moved {
+        animal: Animal,
+        from: usize,
+        to: usize,
+        /// The cage in the old layout that the cage `to` was matched with, or
+        /// `None` if it is a new cage
+        was: Option<usize>,
+    },
+}
+
+/// `LayoutDiff` lists the animals that changed cages between two layouts.
+#[derive(Serialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct LayoutDiff {
+    pub changes: Vec<Change>,
+}
+
+impl LayoutDiff {
+    /// `new` computes which animals moved from `old` to `new`.
+    ///
+    /// Animals are identified by their name and their `normalize`d species, so
+    /// respelling a species doesn't move its animals. Since the position of
+    /// a cage may change between layouts, every cage in `new` is first matched
+    /// with a cage in `old`: the one with the same predator species or else the
+    /// one it shares the most animals with. Only animals that don't end up in
+    /// the matched cage count as moved.
+    pub fn new(old: &[Cage], new: &[Cage]) -> Self {
+        let old_cages: HashMap<_, _> = old
+            .iter()
+            .enumerate()
+            .flat_map(|(i, cage)| cage.animals.iter().map(move |animal| (key(animal), i)))
+            .collect();
+        let new_cages: HashMap<_, _> = new
+            .iter()
+            .enumerate()
+            .flat_map(|(i, cage)| cage.animals.iter().map(move |animal| (key(animal), i)))
+            .collect();
+
+        let matching = match_cages(old, new, &old_cages);
+
+        let mut changes = vec![];
+        for (i, cage) in new.iter().enumerate() {
+            for animal in &cage.animals {
+                match old_cages.get(&key(animal)) {
+                    None => changes.push(Change::Added {
+                        animal: animal.clone(),
+                        to: i + 1,
+                    }),
+                    Some(&from) if matching[i] != Some(from) => changes.push(Change::Moved {
+                        animal: animal.clone(),
+                        from: from + 1,
+                        to: i + 1,
+                        was: matching[i].map(|j| j + 1),
+                    }),
+                    Some(_) => {}
+                }
+            }
+        }
+        for (i, cage) in old.iter().enumerate() {
+            for animal in &cage.animals {
+                if !new_cages.contains_key(&key(animal)) {
+                    changes.push(Change::Removed {
+                        animal: animal.clone(),
+                        from: i + 1,
+                    });
+                }
+            }
+        }
+
+        Self { changes }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+/// `Watch` keeps the last good layout of an inventory that changes over time,
+/// and tells how every new layout differs from it.
+#[derive(Clone, Debug, Default)]
+pub struct Watch {
+    last_good: Option<Vec<Cage>>,
+}
+
+/// `Reload` is what `Watch::reload` found.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Reload {
+    /// The first layout, with how many animals and cages it has
+    First { animals: usize, cages: usize },
+    /// How the layout differs from the last good one
+    Changed(LayoutDiff),
+}
+
+impl Watch {
+    /// `reload` makes a new layout with `load` and compares it with the last
+    /// good one, which it then replaces.
+    ///
+    /// # Errors
+    ///
+    /// This method errors if `load` does. The last good layout is kept then.
+    pub fn reload(&mut self, load: impl FnOnce() -> Result<Vec<Cage>>) -> Result<Reload> {
+        let cages = load()?;
+        let reload = match &self.last_good {
+            Some(old) => Reload::Changed(LayoutDiff::new(old, &cages)),
+            None => Reload::First {
+                animals: cages.iter().map(|cage| cage.animals.len()).sum(),
+                cages: cages.len(),
+            },
+        };
+        self.last_good = Some(cages);
+        Ok(reload)
+    }
+
+    /// `last_good` is the layout of the last successful `reload`.
+    pub fn last_good(&self) -> Option<&[Cage]> {
+        self.last_good.as_deref()
+    }
+}
+
+/// `matching` matches every cage in `new` with a cage in `old`, like
+/// `LayoutDiff::new` does.
+pub(crate) fn matching(old: &[Cage], new: &[Cage]) -> Vec<Option<usize>> {
+    let old_cages: HashMap<_, _> = old
+        .iter()
+        .enumerate()
+        .flat_map(|(i, cage)| cage.animals.iter().map(move |animal| (key(animal), i)))
+        .collect();
+    match_cages(old, new, &old_cages)
+}
+
+fn key(animal: &Animal) -> (&str, String) {
+    (&animal.name, normalize(&animal.species))
+}
+
+/// Greedily matches every new cage with an unmatched old cage. Cages with the
+/// same predator species are matched first, the remaining ones with the cage
+/// they share the most animals with.
+fn match_cages(
+    old: &[Cage],
+    new: &[Cage],
+    old_cages: &HashMap<(&str, String), usize>,
+) -> Vec<Option<usize>> {
+    let predator = |cage: &Cage| cage.predator().map(|predator| normalize(&predator.species));
+
+    let mut candidates = vec![];
+    for (i, cage) in new.iter().enumerate() {
+        let mut shared = vec![0; old.len()];
+        for animal in &cage.animals {
+            if let Some(&j) = old_cages.get(&key(animal)) {
+                shared[j] += 1;
+            }
+        }
+        let new_predator = predator(cage);
+        for (j, count) in shared.into_iter().enumerate() {
+            let same_predator = new_predator.is_some() && new_predator == predator(&old[j]);
+            if same_predator || count > 0 {
+                candidates.push((Reverse(same_predator), Reverse(count), i, j));
+            }
+        }
+    }
+    // Ties are broken by the position of the cages
+    candidates.sort();
+
+    let mut matching = vec![None; new.len()];
+    let mut matched_old = vec![false; old.len()];
+    for (_, _, i, j) in candidates {
+        if matching[i].is_none() && !matched_old[j] {
+            matching[i] = Some(j);
+            matched_old[j] = true;
+        }
+    }
+    matching
+}
+
+impl Display for Change {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Added { animal, to } => {
+                write!(f, "+ {} ({}): new cage {}", animal.name, animal.species, to)
+            }
+            Self::Removed { animal, from } => {
+                write!(
+                    f,
+                    "- {} ({}): old cage {}",
+                    animal.name, animal.species, from
+                )
+            }
+            // Cages may be renumbered, so the old number of the new cage is
+            // given as well
+            Self::Moved {
+                animal,
+                from,
+                to,
+                was,
+            } => {
+                write!(
+                    f,
+                    "~ {} ({}): old cage {} -> new cage {}",
+                    animal.name, animal.species, from, to
+                )?;
+                match was {
+                    Some(was) => write!(f, " (was old cage {})", was),
+                    None => write!(f, " (a new cage)"),
+                }
+            }
+        }
+    }
+}
+
+impl Display for Reload {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::First { animals, cages } => {
+                writeln!(f, "caged {} animals in {} cages", animals, cages)
+            }
+            Self::Changed(diff) => write!(f, "{}", diff),
+        }
+    }
+}
+
+impl Display for LayoutDiff {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "no animal changed cages");
+        }
+        for change in &self.changes {
+            writeln!(f, "{}", change)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::animal::animal;
+    use crate::error::Error;
+
+    #[test]
+    fn layout_diff() {
+        let old = [
+            Cage::from(vec![
+                animal("A1", "S1", 10, false),
+                animal("A2", "S1", 10, false),
+            ]),
+            Cage::from(vec![
+                animal("B1", "S2", 10, false),
+                animal("C1", "S3", 10, false),
+            ]),
+        ];
+        // A new cage in front shifts all other cages
+        let new = [
+            Cage::from(vec![animal("D1", "S4", 10, false)]),
+            Cage::from(vec![
+                animal("A1", "S1", 10, false),
+                animal("A2", "S1", 10, false),
+                animal("C1", "S3", 10, false),
+            ]),
+            Cage::from(vec![animal("B1", "S2", 10, false)]),
+        ];
+
+        let diff = LayoutDiff::new(&old, &new);
+
+        // `Animal` only compares species, so compare the rendered changes
+        assert_eq!(
+            diff.to_string(),
+            "+ D1 (S4): new cage 1\n\
+             ~ C1 (S3): old cage 2 -> new cage 2 (was old cage 1)\n"
+        );
+        assert!(LayoutDiff::new(&new, &new).is_empty());
+
+        // Animals of the same species are told apart by their name
+        let renamed = [Cage::from(vec![
+            animal("A1", "S1", 10, false),
+            animal("A3", "S1", 10, false),
+        ])];
+        assert_eq!(
+            LayoutDiff::new(&old[..1], &renamed).to_string(),
+            "+ A3 (S1): new cage 1\n\
+             - A2 (S1): old cage 1\n"
+        );
+
+        // Respelling a species doesn't move its animals
+        let respelled = [
+            Cage::from(vec![
+                animal("A1", "s1", 10, false),
+                animal("A2", " S1", 10, false),
+            ]),
+            old[1].clone(),
+        ];
+        assert!(LayoutDiff::new(&old, &respelled).is_empty());
+    }
+
+    #[test]
+    fn watch_reload() {
+        let old = vec![Cage::from(vec![
+            animal("A1", "S1", 10, false),
+            animal("A2", "S1", 10, false),
+        ])];
+        let new = vec![Cage::from(vec![animal("A1", "S1", 10, false)])];
+        let mut watch = Watch::default();
+
+        let reload = watch.reload(|| Ok(old.clone())).unwrap();
+        assert_eq!(reload.to_string(), "caged 2 animals in 1 cages\n");
+
+        // A broken inventory keeps the last good layout
+        let broken = watch.reload(|| Err(Error::NoCarnivore(String::from("S1"))));
+        assert!(broken.is_err());
+        assert_eq!(watch.last_good().map(<[Cage]>::len), Some(1));
+
+        let reload = watch.reload(|| Ok(new.clone())).unwrap();
+        assert_eq!(reload.to_string(), "- A2 (S1): old cage 1\n");
+        assert_eq!(watch.last_good().unwrap()[0].animals.len(), 1);
+    }
+}
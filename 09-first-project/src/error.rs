@@ -1,3 +1,5 @@
+use crate::consistency::Conflict;
+use crate::Animal;
 use std::fmt::{Debug, Display, Formatter};
 
 /// `Error` is the error type of this crate.
@@ -9,7 +11,67 @@ use std::fmt::{Debug, Display, Formatter};
 pub enum Error {
     IOError(std::io::Error),
     Serialization(serde_json::Error),
-    // Add custom variants here
+    /// There is no animal of the requested food species.
+    NoFood {
+        species: String,
+        /// A species of the inventory the user probably meant
+        suggestion: Option<String>,
+    },
+    /// There is no carnivore the food species could be fed to.
+    NoCarnivore(String),
+    /// A food animal is at least as strong as the predator it should be fed to.
+    FoodTooStrong {
+        food: Box<Animal>,
+        predator: Box<Animal>,
+    },
+    /// An inventory record omits a field, that the species catalogue doesn't
+    /// know either.
+    MissingField {
+        animal: String,
+        species: String,
+        field: &'static str,
+    },
+    /// The records of some species contradict each other.
+    Inconsistent(Vec<Conflict>),
+    /// A value, e.g. of a command line option, couldn't be parsed.
+    InvalidValue(String),
+    /// The zoo map has fewer enclosure slots than there are cages.
+    TooFewSlots {
+        cages: usize,
+        slots: usize,
+    },
+    /// The cages can't be placed on the zoo map without a predator living
+    /// next to its prey.
+    NoMapLayout {
+        cages: usize,
+    },
+    /// An animal is too strong for the transport crate.
+    TooHeavy {
+        animal: Box<Animal>,
+        max_strength: usize,
+    },
+    /// The cage layout is riskier than allowed.
+    TooRisky {
+        risk: f64,
+        max_risk: f64,
+    },
+    /// The animals of a site of a federation can't be caged.
+    Site {
+        site: String,
+        error: Box<Error>,
+    },
+    /// The configuration file couldn't be read.
+    Config {
+        path: std::path::PathBuf,
+        message: String,
+    },
+    /// A filter expression couldn't be parsed. `position` is the byte offset
+    /// in `expression` at which the problem was found.
+    Filter {
+        expression: String,
+        position: usize,
+        message: String,
+    },
 }
 
 // You should implement the `Display` trait for your error, so that crates can
@@ -22,7 +84,80 @@ impl Display for Error {
         match self {
             Self::IOError(err) => write!(f, "{}", err),
             Self::Serialization(err) => write!(f, "{}", err),
-            // Deal with more variants here
+            Self::NoFood {
+                species,
+                suggestion: Some(suggestion),
+            } => write!(
+                f,
+                "there is no animal of species `{}`, did you mean `{}`?",
+                species, suggestion
+            ),
+            Self::NoFood { species, .. } => {
+                write!(f, "there is no animal of species `{}`", species)
+            }
+            Self::NoCarnivore(food) => write!(f, "there is no carnivore to feed `{}` to", food),
+            Self::FoodTooStrong { food, predator } => write!(
+                f,
+                "{} the {} ({}) is too strong to be fed to {} the {} ({})",
+                food.name,
+                food.species,
+                food.strength,
+                predator.name,
+                predator.species,
+                predator.strength
+            ),
+            Self::MissingField {
+                animal,
+                species,
+                field,
+            } => write!(
+                f,
+                "{} the {} has no `{}` and the catalogue doesn't provide one",
+                animal, species, field
+            ),
+            Self::Inconsistent(conflicts) => {
+                write!(f, "the inventory is inconsistent:")?;
+                for conflict in conflicts {
+                    write!(f, "\n  - {}", conflict)?;
+                }
+                Ok(())
+            }
+            Self::InvalidValue(message) => write!(f, "{}", message),
+            Self::TooFewSlots { cages, slots } => write!(
+                f,
+                "the map has {} enclosure slots, but there are {} cages",
+                slots, cages
+            ),
+            Self::NoMapLayout { cages } => write!(
+                f,
+                "the {} cages can't be placed on the map without a predator next to its prey",
+                cages
+            ),
+            Self::TooHeavy {
+                animal,
+                max_strength,
+            } => write!(
+                f,
+                "{} the {} ({}) is too strong for a transport crate of strength {}",
+                animal.name, animal.species, animal.strength, max_strength
+            ),
+            Self::TooRisky { risk, max_risk } => write!(
+                f,
+                "the cage layout has a risk of {:.2}, more than the maximum of {:.2}",
+                risk, max_risk
+            ),
+            Self::Site { site, error } => write!(f, "site {}: {}", site, error),
+            Self::Config { path, message } => write!(f, "{}: {}", path.display(), message),
+            Self::Filter {
+                expression,
+                position,
+                message,
+            } => {
+                let column = expression[..*position].chars().count();
+                writeln!(f, "invalid filter expression: {}", message)?;
+                writeln!(f, "    {}", expression)?;
+                write!(f, "    {:>width$}", "^", width = column + 1)
+            }
         }
     }
 }
@@ -34,7 +169,6 @@ impl Display for Error {
 /// If you have an `Error` type, you should also have your own `Result` type
 pub type Result<T> = std::result::Result<T, Error>;
 
-
 // Nothing to change here.
 //
 // If your crates doesn't deal with errors from other crates, they can be
@@ -58,6 +192,6 @@ impl From<serde_json::Error> for Error {
 // example program.
 impl Debug for Error {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.to_string())
+        write!(f, "{}", self)
     }
 }
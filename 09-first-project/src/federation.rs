@@ -0,0 +1,385 @@
+//! Transfers between the sites of a federation of zoos.
+//!
+//! Every `Site` has its own inventory and cages it with the same `Caging`.
+//! Moving the animals of a species from one site to another may save cages,
+//! e.g. if a herbivore can join a predator at the other site, or even out how
+//! many species the sites keep. `plan` searches for such transfers and lists
+//! them in a `Manifest`.
+//!
+//! Species are always moved as a whole, so that they stay one group. The food
+//! of a site stays where it is, and no species is moved to a site that would
+//! feed it to its predator.
+
+use crate::caging::{Caging, Layout};
+use crate::error::Error;
+use crate::feeding::csv_field;
+use crate::health::Enclosure;
+use crate::species::{normalize, same_species};
+use crate::{Animal, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fmt::{self, Display, Formatter, Write};
+use std::str::FromStr;
+
+/// How many transfers of species `plan` proposes at most.
+pub const MAX_TRANSFERS: usize = 100;
+
+/// `Site` is a single zoo of a federation.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Site {
+    pub name: String,
+    pub animals: Vec<Animal>,
+    /// The species fed to the strongest carnivore of the site
+    #[serde(default)]
+    pub food: Option<String>,
+}
+
+/// `Goal` is what the transfers should achieve.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Goal {
+    /// Use as few cages as possible, in all sites together
+    Cages,
+    /// Even out how many species the sites keep, then use as few cages as
+    /// possible
+    Balance,
+}
+
+impl FromStr for Goal {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "cages" => Ok(Self::Cages),
+            "balance" => Ok(Self::Balance),
+            _ => Err(Error::InvalidValue(format!(
+                "invalid value `{}`, expected `cages` or `balance`",
+                s
+            ))),
+        }
+    }
+}
+
+/// `Transfer` moves an animal from a cage of one site to a cage of another.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct Transfer {
+    pub animal: Animal,
+    pub from_site: String,
+    /// Where the animal lives before the transfers
+    pub from: Enclosure,
+    pub to_site: String,
+    /// Where the animal lives after the transfers
+    pub to: Enclosure,
+}
+
+/// `Manifest` lists the transfers `plan` proposes.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct Manifest {
+    pub transfers: Vec<Transfer>,
+    /// How many cages all sites need, before and after the transfers,
+    /// including quarantine and isolation
+    pub cages_before: usize,
+    pub cages_after: usize,
+    /// The layouts of the sites after the transfers, in the order of the
+    /// sites
+    pub layouts: Vec<Layout>,
+}
+
+/// The animals of a site, with the index of the site each one comes from.
+type Inventory = Vec<(Animal, usize)>;
+
+/// `plan` proposes transfers between the `sites` towards the `goal`.
+///
+/// This is a local search: of all the ways to move a species to another site,
+/// the one that gets closest to the goal is made, as long as every site can
+/// still cage its animals, until no move gets any closer or `MAX_TRANSFERS`
+/// moves were made.
+///
+/// # Errors
+///
+/// This function errors with `Error::Site`, if a site can't cage its animals
+/// to begin with.
+pub fn plan(sites: &[Site], caging: &Caging, goal: Goal) -> Result<Manifest> {
+    let mut inventories: Vec<Inventory> = sites
+        .iter()
+        .enumerate()
+        .map(|(i, site)| site.animals.iter().map(|a| (a.clone(), i)).collect())
+        .collect();
+    let before = sites
+        .iter()
+        .zip(&inventories)
+        .map(|(site, inventory)| cage(site, inventory, caging))
+        .collect::<Result<Vec<_>>>()?;
+    let mut layouts = before.clone();
+
+    for _ in 0..MAX_TRANSFERS {
+        let current = score(goal, &inventories, &layouts);
+        let mut best = None;
+        for from in 0..sites.len() {
+            for species in movable(&sites[from], &inventories[from]) {
+                for to in (0..sites.len()).filter(|&to| to != from && !feeds(&sites[to], &species))
+                {
+                    let (source, target) = moved(&inventories, from, to, &species);
+                    let layout = |site, inventory| cage(&sites[site], inventory, caging).ok();
+                    let (Some(source_layout), Some(target_layout)) =
+                        (layout(from, &source), layout(to, &target))
+                    else {
+                        continue;
+                    };
+
+                    let mut candidate = inventories.clone();
+                    candidate[from] = source;
+                    candidate[to] = target;
+                    let mut candidate_layouts = layouts.clone();
+                    candidate_layouts[from] = source_layout;
+                    candidate_layouts[to] = target_layout;
+
+                    let candidate_score = score(goal, &candidate, &candidate_layouts);
+                    let better = match &best {
+                        None => candidate_score < current,
+                        Some((best_score, _, _)) => candidate_score < *best_score,
+                    };
+                    if better {
+                        best = Some((candidate_score, candidate, candidate_layouts));
+                    }
+                }
+            }
+        }
+
+        match best {
+            Some((_, candidate, candidate_layouts)) => {
+                inventories = candidate;
+                layouts = candidate_layouts;
+            }
+            None => break,
+        }
+    }
+
+    let mut transfers = vec![];
+    for (to, inventory) in inventories.iter().enumerate() {
+        for (animal, from) in inventory.iter().filter(|(_, from)| *from != to) {
+            transfers.push(Transfer {
+                animal: animal.clone(),
+                from_site: sites[*from].name.clone(),
+                from: Enclosure::of(&before[*from], animal).expect("the animal was caged"),
+                to_site: sites[to].name.clone(),
+                to: Enclosure::of(&layouts[to], animal).expect("the animal was caged"),
+            });
+        }
+    }
+    transfers.sort_by(|a, b| (&a.from_site, a.from).cmp(&(&b.from_site, b.from)));
+
+    Ok(Manifest {
+        transfers,
+        cages_before: before.iter().map(cages).sum(),
+        cages_after: layouts.iter().map(cages).sum(),
+        layouts,
+    })
+}
+
+fn cage(site: &Site, inventory: &Inventory, caging: &Caging) -> Result<Layout> {
+    let animals = inventory.iter().map(|(animal, _)| animal.clone()).collect();
+    caging
+        .cage(animals, site.food.as_deref())
+        .map_err(|error| Error::Site {
+            site: site.name.clone(),
+            error: Box::new(error),
+        })
+}
+
+fn cages(layout: &Layout) -> usize {
+    layout.cages.len() + layout.quarantine.len() + layout.isolation.len()
+}
+
+/// The species of a site that may be moved: all but its food.
+fn movable(site: &Site, inventory: &Inventory) -> BTreeSet<String> {
+    inventory
+        .iter()
+        .map(|(animal, _)| &animal.species)
+        .filter(|species| {
+            site.food
+                .as_deref()
+                .is_none_or(|food| !same_species(species, food))
+        })
+        .map(|species| normalize(species))
+        .collect()
+}
+
+/// `feeds` tells if `site` feeds the `species` to its strongest carnivore.
+fn feeds(site: &Site, species: &str) -> bool {
+    site.food
+        .as_deref()
+        .is_some_and(|food| same_species(species, food))
+}
+
+/// The inventories of the sites `from` and `to`, after moving the `species`.
+fn moved(
+    inventories: &[Inventory],
+    from: usize,
+    to: usize,
+    species: &str,
+) -> (Inventory, Inventory) {
+    let (leaving, source): (Inventory, Inventory) = inventories[from]
+        .iter()
+        .cloned()
+        .partition(|(animal, _)| normalize(&animal.species) == species);
+    let mut target = inventories[to].clone();
+    target.extend(leaving);
+    (source, target)
+}
+
+/// Lower is better, compared lexicographically.
+fn score(goal: Goal, inventories: &[Inventory], layouts: &[Layout]) -> (usize, usize) {
+    let cages = layouts.iter().map(cages).sum();
+    match goal {
+        Goal::Cages => (cages, 0),
+        Goal::Balance => {
+            let species = inventories.iter().map(|inventory| {
+                inventory
+                    .iter()
+                    .map(|(animal, _)| normalize(&animal.species))
+                    .collect::<BTreeSet<_>>()
+                    .len()
+            });
+            let max = species.clone().max().unwrap_or(0);
+            let min = species.min().unwrap_or(0);
+            (max - min, cages)
+        }
+    }
+}
+
+impl Manifest {
+    /// `to_csv` exports the transfers as CSV, with a header line.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("name,species,strength,from_site,from,to_site,to\n");
+        for transfer in &self.transfers {
+            let _ = writeln!(
+                csv,
+                "{},{},{},{},{},{},{}",
+                csv_field(&transfer.animal.name),
+                csv_field(&transfer.animal.species),
+                transfer.animal.strength,
+                csv_field(&transfer.from_site),
+                transfer.from,
+                csv_field(&transfer.to_site),
+                transfer.to
+            );
+        }
+        csv
+    }
+}
+
+impl Display for Manifest {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.transfers.is_empty() {
+            writeln!(f, "No transfer is worth it.")?;
+        }
+        for transfer in &self.transfers {
+            writeln!(
+                f,
+                "{} the {}: {}, {} -> {}, {}",
+                transfer.animal.name,
+                transfer.animal.species,
+                transfer.from_site,
+                transfer.from,
+                transfer.to_site,
+                transfer.to
+            )?;
+        }
+        writeln!(f, "Cages: {} -> {}", self.cages_before, self.cages_after)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::animal::animal;
+
+    fn site(name: &str, animals: Vec<Animal>) -> Site {
+        Site {
+            name: String::from(name),
+            animals,
+            food: None,
+        }
+    }
+
+    #[test]
+    fn fewer_cages() {
+        let sites = [
+            site("Berlin", vec![animal("Leo", "Lion", 20, true)]),
+            site(
+                "Vienna",
+                vec![
+                    animal("Marty", "Zebra", 30, false),
+                    animal("Stripes", "Zebra", 25, false),
+                ],
+            ),
+        ];
+
+        let manifest = plan(&sites, &Caging::default(), Goal::Cages).unwrap();
+
+        assert_eq!((manifest.cages_before, manifest.cages_after), (2, 1));
+        let transfers: Vec<_> = manifest
+            .transfers
+            .iter()
+            .map(|t| (t.animal.name.as_str(), t.from_site.as_str(), t.from, t.to))
+            .collect();
+        // Either way round works, Berlin comes first
+        assert_eq!(
+            transfers,
+            [("Leo", "Berlin", Enclosure::Cage(1), Enclosure::Cage(1))]
+        );
+        assert_eq!(
+            manifest.to_csv().lines().nth(1),
+            Some("Leo,Lion,20,Berlin,Cage 1,Vienna,Cage 1")
+        );
+    }
+
+    #[test]
+    fn balance() {
+        let sites = [
+            site(
+                "Berlin",
+                vec![
+                    animal("Leo", "Lion", 20, true),
+                    animal("Marty", "Zebra", 10, false),
+                    animal("Bambi", "Deer", 5, false),
+                ],
+            ),
+            site("Vienna", vec![]),
+        ];
+
+        let manifest = plan(&sites, &Caging::default(), Goal::Balance).unwrap();
+
+        // Moving the lion evens out the species, without another cage
+        assert_eq!(manifest.transfers.len(), 1);
+        assert_eq!(manifest.transfers[0].animal.name, "Leo");
+        assert_eq!(manifest.cages_after, 2);
+        assert!(plan(&sites, &Caging::default(), Goal::Cages)
+            .unwrap()
+            .transfers
+            .is_empty());
+    }
+
+    #[test]
+    fn no_transfer_to_become_food() {
+        let sites = [
+            Site {
+                food: Some(String::from("zebra")),
+                ..site(
+                    "Berlin",
+                    vec![
+                        animal("Leo", "Lion", 20, true),
+                        animal("Stripes", "Zebra", 2, false),
+                    ],
+                )
+            },
+            site("Vienna", vec![animal("Marty", "Zebra", 3, false)]),
+        ];
+
+        // Marty would save a cage, but only by being fed to Leo
+        let manifest = plan(&sites, &Caging::default(), Goal::Cages).unwrap();
+        assert!(manifest.transfers.is_empty());
+        assert_eq!(manifest.cages_after, 2);
+    }
+}
@@ -0,0 +1,290 @@
+//! Daily feeding plans for a cage layout.
+//!
+//! The `Catalogue` says what the animals of a species eat, how much and when.
+//! From this, `FeedingPlan::new` lists every meal of every cage, which can be
+//! exported as CSV for the keepers, or as an iCalendar file for their
+//! calendars.
+//!
+//! [`Catalogue`]: crate::catalogue::Catalogue
+
+use crate::catalogue::Catalogue;
+use crate::species::normalize;
+use crate::{Cage, CageKind};
+use chrono::{NaiveDate, NaiveTime};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+/// How long a meal takes in the calendar, in minutes.
+const MEAL_MINUTES: u32 = 15;
+
+/// `Diet` is what the animals of a species get to eat every day.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Diet {
+    /// What they are fed, e.g. `"meat"` or `"hay"`
+    pub food: String,
+    /// How much every animal gets per meal
+    pub quantity: f64,
+    /// The unit of the `quantity`
+    #[serde(default = "default_unit")]
+    pub unit: String,
+    /// When they are fed, e.g. `["08:00", "16:00"]`
+    pub times: Vec<NaiveTime>,
+}
+
+fn default_unit() -> String {
+    String::from("kg")
+}
+
+/// `Meal` is a single feeding of the animals of a species in a cage.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct Meal {
+    /// The position of the cage in the layout, starting at 1
+    pub cage: usize,
+    pub species: String,
+    pub animals: usize,
+    pub food: String,
+    /// How much the animals get together
+    pub quantity: f64,
+    pub unit: String,
+    pub time: NaiveTime,
+    pub keeper: Option<String>,
+}
+
+/// `FeedingPlan` lists the daily meals of a cage layout.
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+pub struct FeedingPlan {
+    /// The meals, by cage and time
+    pub meals: Vec<Meal>,
+    /// Cages where carnivores and herbivores are fed in the same place
+    pub shared_areas: Vec<usize>,
+    /// Species the catalogue has no diet for
+    pub unknown: Vec<String>,
+}
+
+impl FeedingPlan {
+    /// `new` plans the meals of the `cages`, according to the diets in the
+    /// `catalogue`.
+    ///
+    /// The `keepers` take turns: every cage is fed by the next one. Without
+    /// keepers, the meals aren't assigned to anyone.
+    pub fn new(cages: &[Cage], catalogue: &Catalogue, keepers: &[String]) -> Self {
+        let mut plan = Self::default();
+        let mut unknown = BTreeSet::new();
+
+        for (i, cage) in cages.iter().enumerate() {
+            let keeper = (!keepers.is_empty()).then(|| keepers[i % keepers.len()].clone());
+
+            // Group the animals of the cage by species, in order of appearance
+            let mut species: Vec<(&str, usize)> = vec![];
+            for animal in &cage.animals {
+                let name = normalize(&animal.species);
+                match species.iter_mut().find(|(s, _)| normalize(s) == name) {
+                    Some((_, count)) => *count += 1,
+                    None => species.push((&animal.species, 1)),
+                }
+            }
+
+            for (name, animals) in species {
+                let diet = match catalogue.info(name).and_then(|info| info.diet.as_ref()) {
+                    Some(diet) => diet,
+                    None => {
+                        unknown.insert(name.to_string());
+                        continue;
+                    }
+                };
+                for &time in &diet.times {
+                    plan.meals.push(Meal {
+                        cage: i + 1,
+                        species: name.to_string(),
+                        animals,
+                        food: diet.food.clone(),
+                        quantity: diet.quantity * animals as f64,
+                        unit: diet.unit.clone(),
+                        time,
+                        keeper: keeper.clone(),
+                    });
+                }
+            }
+
+            // Carnivores and herbivores of a cage share its feeding area
+            if cage.kind() == CageKind::Mixed {
+                plan.shared_areas.push(i + 1);
+            }
+        }
+
+        plan.meals.sort_by_key(|meal| (meal.cage, meal.time));
+        plan.unknown = unknown.into_iter().collect();
+        plan
+    }
+
+    /// `to_csv` exports the meals as CSV, with a header line.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("cage,time,species,animals,food,quantity,unit,keeper,shared\n");
+        for meal in &self.meals {
+            let _ = writeln!(
+                csv,
+                "{},{},{},{},{},{},{},{},{}",
+                meal.cage,
+                meal.time.format("%H:%M"),
+                csv_field(&meal.species),
+                meal.animals,
+                csv_field(&meal.food),
+                meal.quantity,
+                csv_field(&meal.unit),
+                csv_field(meal.keeper.as_deref().unwrap_or("")),
+                self.shared_areas.contains(&meal.cage)
+            );
+        }
+        csv
+    }
+
+    /// `to_ical` exports the meals as iCalendar events, which repeat daily
+    /// from `start` on.
+    pub fn to_ical(&self, start: NaiveDate) -> String {
+        let mut lines = vec![
+            String::from("BEGIN:VCALENDAR"),
+            String::from("VERSION:2.0"),
+            String::from("PRODID:-//my-zoo//feeding plan//EN"),
+        ];
+        for meal in &self.meals {
+            let mut summary = format!(
+                "Feed {} {} {} to the {} in cage {}",
+                meal.quantity, meal.unit, meal.food, meal.species, meal.cage
+            );
+            if self.shared_areas.contains(&meal.cage) {
+                summary.push_str(" (shared feeding area)");
+            }
+            lines.extend([
+                String::from("BEGIN:VEVENT"),
+                format!(
+                    "UID:{}-{}-{}@my-zoo",
+                    meal.cage,
+                    normalize(&meal.species).replace(' ', "-"),
+                    meal.time.format("%H%M")
+                ),
+                format!("DTSTAMP:{}T000000Z", start.format("%Y%m%d")),
+                format!(
+                    "DTSTART:{}T{}",
+                    start.format("%Y%m%d"),
+                    meal.time.format("%H%M%S")
+                ),
+                format!("DURATION:PT{}M", MEAL_MINUTES),
+                String::from("RRULE:FREQ=DAILY"),
+                format!("SUMMARY:{}", ical_text(&summary)),
+            ]);
+            if let Some(keeper) = &meal.keeper {
+                lines.push(format!("DESCRIPTION:Keeper: {}", ical_text(keeper)));
+            }
+            lines.push(String::from("END:VEVENT"));
+        }
+        lines.push(String::from("END:VCALENDAR"));
+
+        lines.iter().map(|line| fold(line) + "\r\n").collect()
+    }
+}
+
+/// Quotes `field` if it contains a character with a meaning in CSV.
+pub(crate) fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Escapes the characters with a meaning in iCalendar text values.
+fn ical_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Folds an iCalendar content line after at most 75 bytes, without
+/// splitting characters.
+fn fold(line: &str) -> String {
+    let mut folded = String::new();
+    let mut length = 0;
+    for c in line.chars() {
+        if length + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            length = 1;
+        }
+        folded.push(c);
+        length += c.len_utf8();
+    }
+    folded
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::animal::animal;
+
+    fn catalogue() -> Catalogue {
+        serde_json::from_str(
+            r#"{
+                "Lion": { "diet": { "food": "meat", "quantity": 7.5, "times": ["16:00"] } },
+                "Zebra": { "diet": { "food": "hay, fresh", "quantity": 10, "times": ["08:00", "18:00"] } }
+            }"#,
+        )
+        .unwrap()
+    }
+
+    fn cages() -> Vec<Cage> {
+        vec![
+            Cage::from(vec![
+                animal("Leo", "Lion", 10, true),
+                animal("Nala", "Lion", 9, true),
+                animal("Marty", "Zebra", 20, false),
+            ]),
+            Cage::from(vec![animal("Polly", "Parrot", 1, false)]),
+        ]
+    }
+
+    #[test]
+    fn feeding_plan() {
+        let keepers = [String::from("Alice"), String::from("Bob")];
+        let plan = FeedingPlan::new(&cages(), &catalogue(), &keepers);
+
+        let meals: Vec<_> = plan
+            .meals
+            .iter()
+            .map(|meal| (meal.species.as_str(), meal.quantity, meal.time.to_string()))
+            .collect();
+        assert_eq!(
+            meals,
+            [
+                ("Zebra", 10.0, String::from("08:00:00")),
+                ("Lion", 15.0, String::from("16:00:00")),
+                ("Zebra", 10.0, String::from("18:00:00")),
+            ]
+        );
+        assert_eq!(plan.meals[0].keeper.as_deref(), Some("Alice"));
+        assert_eq!(plan.shared_areas, [1]);
+        assert_eq!(plan.unknown, ["Parrot"]);
+    }
+
+    #[test]
+    fn exports() {
+        let plan = FeedingPlan::new(&cages(), &catalogue(), &[]);
+
+        let csv = plan.to_csv();
+        assert_eq!(
+            csv.lines().nth(1),
+            Some("1,08:00,Zebra,1,\"hay, fresh\",10,kg,,true")
+        );
+
+        let ical = plan.to_ical(NaiveDate::from_ymd_opt(2024, 5, 1).unwrap());
+        assert!(ical.starts_with("BEGIN:VCALENDAR\r\n"));
+        assert!(ical.contains("DTSTART:20240501T160000\r\n"));
+        assert!(ical.contains(
+            "SUMMARY:Feed 10 kg hay\\, fresh to the Zebra in cage 1 (shared feeding area)\r\n"
+        ));
+        assert_eq!(
+            fold(&"x".repeat(100)),
+            format!("{}\r\n {}", "x".repeat(75), "x".repeat(25))
+        );
+    }
+}
@@ -0,0 +1,528 @@
+use crate::animal::{FieldKind, FieldValue};
+use crate::error::Error;
+use crate::species::same_species;
+use crate::{Animal, Result};
+use chrono::NaiveDate;
+use std::str::FromStr;
+
+/// `Filter` is a parsed filter expression over the fields of an `Animal`.
+///
+/// Expressions compare fields with values and combine those comparisons with
+/// `&&`, `||`, `!` and parentheses:
+///
+/// ```text
+/// carnivore && strength > 20 && species != Dog
+/// !carnivore || (name == "Kevin" && strength >= 50)
+/// ```
+///
+/// Boolean fields can be used on their own, text values can either be quoted
+/// (`"Gustav 'SegFault' Rex"`) or written as a bare word (`Dog`). Dates are
+/// quoted as well (`arrival >= "2024-05-01"`). Species are compared like
+/// `same_species` does, so `species == "african lion"` matches `African Lion`.
+#[derive(Clone, Debug)]
+pub struct Filter {
+    expr: Expr,
+}
+
+#[derive(Clone, Debug)]
+enum Expr {
+    Or(Box<Expr>, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Flag(String),
+    Compare {
+        field: String,
+        op: Op,
+        value: Literal,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Clone, Debug)]
+enum Literal {
+    Number(usize),
+    Text(String),
+    Bool(bool),
+    Date(NaiveDate),
+}
+
+impl Filter {
+    /// `parse` parses and type checks a filter expression.
+    ///
+    /// # Errors
+    ///
+    /// This function errors if the expression is malformed, refers to a field
+    /// `Animal` doesn't have or compares a field with a value of another type.
+    pub fn parse(expression: &str) -> Result<Self> {
+        let tokens = tokenize(expression)?;
+        let mut parser = Parser {
+            source: expression,
+            tokens: &tokens,
+            pos: 0,
+        };
+        let expr = parser.expr()?;
+        if let Some(token) = parser.peek() {
+            return Err(parser.error_at(
+                token.start,
+                format!("unexpected {}, expected `&&` or `||`", token.kind),
+            ));
+        }
+        Ok(Self { expr })
+    }
+
+    /// `matches` evaluates the filter for a single animal.
+    pub fn matches(&self, animal: &Animal) -> bool {
+        self.expr.eval(animal)
+    }
+
+    /// `apply` keeps only the animals matching the filter.
+    pub fn apply(&self, mut animals: Vec<Animal>) -> Vec<Animal> {
+        animals.retain(|animal| self.matches(animal));
+        animals
+    }
+}
+
+impl FromStr for Filter {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::parse(s)
+    }
+}
+
+impl Expr {
+    fn eval(&self, animal: &Animal) -> bool {
+        match self {
+            Self::Or(lhs, rhs) => lhs.eval(animal) || rhs.eval(animal),
+            Self::And(lhs, rhs) => lhs.eval(animal) && rhs.eval(animal),
+            Self::Not(expr) => !expr.eval(animal),
+            Self::Flag(field) => matches!(animal.field(field), Some(FieldValue::Bool(true))),
+            Self::Compare { field, op, value } => match (animal.field(field), value) {
+                // Text can only be compared for (in)equality
+                (Some(FieldValue::Text(lhs)), Literal::Text(rhs)) if field == "species" => {
+                    (*op == Op::Eq) == same_species(lhs, rhs)
+                }
+                (Some(FieldValue::Number(lhs)), Literal::Number(rhs)) => op.compare(&lhs, rhs),
+                (Some(FieldValue::Text(lhs)), Literal::Text(rhs)) => op.compare(lhs, rhs.as_str()),
+                (Some(FieldValue::Bool(lhs)), Literal::Bool(rhs)) => op.compare(&lhs, rhs),
+                (Some(FieldValue::Date(Some(lhs))), Literal::Date(rhs)) => op.compare(&lhs, rhs),
+                _ => false,
+            },
+        }
+    }
+}
+
+impl Op {
+    fn compare<T: PartialOrd + ?Sized>(self, lhs: &T, rhs: &T) -> bool {
+        match self {
+            Self::Eq => lhs == rhs,
+            Self::Ne => lhs != rhs,
+            Self::Lt => lhs < rhs,
+            Self::Le => lhs <= rhs,
+            Self::Gt => lhs > rhs,
+            Self::Ge => lhs >= rhs,
+        }
+    }
+
+    fn is_ordering(self) -> bool {
+        !matches!(self, Self::Eq | Self::Ne)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum TokenKind {
+    Ident(String),
+    Number(usize),
+    Text(String),
+    Op(Op),
+    And,
+    Or,
+    Not,
+    LParen,
+    RParen,
+}
+
+impl std::fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Ident(ident) => write!(f, "`{}`", ident),
+            Self::Number(number) => write!(f, "number `{}`", number),
+            Self::Text(text) => write!(f, "string \"{}\"", text),
+            Self::Op(op) => write!(f, "`{}`", op.as_str()),
+            Self::And => write!(f, "`&&`"),
+            Self::Or => write!(f, "`||`"),
+            Self::Not => write!(f, "`!`"),
+            Self::LParen => write!(f, "`(`"),
+            Self::RParen => write!(f, "`)`"),
+        }
+    }
+}
+
+impl Op {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Eq => "==",
+            Self::Ne => "!=",
+            Self::Lt => "<",
+            Self::Le => "<=",
+            Self::Gt => ">",
+            Self::Ge => ">=",
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Token {
+    kind: TokenKind,
+    start: usize,
+}
+
+fn filter_error(source: &str, position: usize, message: String) -> Error {
+    Error::Filter {
+        expression: source.to_string(),
+        position,
+        message,
+    }
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>> {
+    let mut tokens = vec![];
+    let mut chars = source.char_indices().peekable();
+
+    while let Some((start, c)) = chars.next() {
+        let mut next_is = |expected: char| chars.next_if(|&(_, next)| next == expected).is_some();
+        let kind = match c {
+            c if c.is_whitespace() => continue,
+            '(' => TokenKind::LParen,
+            ')' => TokenKind::RParen,
+            '&' if next_is('&') => TokenKind::And,
+            '|' if next_is('|') => TokenKind::Or,
+            '!' if next_is('=') => TokenKind::Op(Op::Ne),
+            '!' => TokenKind::Not,
+            '=' => {
+                // Accept both `=` and `==`
+                next_is('=');
+                TokenKind::Op(Op::Eq)
+            }
+            '<' if next_is('=') => TokenKind::Op(Op::Le),
+            '<' => TokenKind::Op(Op::Lt),
+            '>' if next_is('=') => TokenKind::Op(Op::Ge),
+            '>' => TokenKind::Op(Op::Gt),
+            '&' | '|' => {
+                return Err(filter_error(
+                    source,
+                    start,
+                    format!("unexpected `{}`, did you mean `{}{}`?", c, c, c),
+                ))
+            }
+            '"' | '\'' => {
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, close)) if close == c => break,
+                        Some((_, other)) => text.push(other),
+                        None => {
+                            return Err(filter_error(
+                                source,
+                                start,
+                                String::from("unterminated string"),
+                            ))
+                        }
+                    }
+                }
+                TokenKind::Text(text)
+            }
+            c if c.is_ascii_digit() => {
+                let mut end = start + c.len_utf8();
+                while let Some((i, d)) = chars.next_if(|(_, d)| d.is_ascii_digit()) {
+                    end = i + d.len_utf8();
+                }
+                let number = source[start..end].parse().map_err(|_| {
+                    filter_error(source, start, String::from("number is too large"))
+                })?;
+                TokenKind::Number(number)
+            }
+            c if is_ident_char(c) => {
+                let mut end = start + c.len_utf8();
+                while let Some((i, d)) = chars.next_if(|&(_, d)| is_ident_char(d)) {
+                    end = i + d.len_utf8();
+                }
+                TokenKind::Ident(source[start..end].to_string())
+            }
+            c => {
+                return Err(filter_error(
+                    source,
+                    start,
+                    format!("unexpected character `{}`", c),
+                ))
+            }
+        };
+        tokens.push(Token { kind, start });
+    }
+
+    Ok(tokens)
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-'
+}
+
+/// A recursive descent parser for the grammar
+///
+/// ```text
+/// expr    := and ( "||" and )*
+/// and     := unary ( "&&" unary )*
+/// unary   := "!" unary | primary
+/// primary := "(" expr ")" | field ( op value )?
+/// ```
+struct Parser<'a> {
+    source: &'a str,
+    tokens: &'a [Token],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&'a Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<&'a Token> {
+        let token = self.tokens.get(self.pos);
+        self.pos += 1;
+        token
+    }
+
+    fn eat(&mut self, kind: &TokenKind) -> bool {
+        if self.peek().map(|token| &token.kind) == Some(kind) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn error_at(&self, position: usize, message: String) -> Error {
+        filter_error(self.source, position, message)
+    }
+
+    fn unexpected(&self, token: Option<&Token>, expected: &str) -> Error {
+        match token {
+            Some(token) => self.error_at(
+                token.start,
+                format!("unexpected {}, expected {}", token.kind, expected),
+            ),
+            None => self.error_at(
+                self.source.len(),
+                format!("unexpected end of expression, expected {}", expected),
+            ),
+        }
+    }
+
+    fn expr(&mut self) -> Result<Expr> {
+        let mut lhs = self.and()?;
+        while self.eat(&TokenKind::Or) {
+            lhs = Expr::Or(Box::new(lhs), Box::new(self.and()?));
+        }
+        Ok(lhs)
+    }
+
+    fn and(&mut self) -> Result<Expr> {
+        let mut lhs = self.unary()?;
+        while self.eat(&TokenKind::And) {
+            lhs = Expr::And(Box::new(lhs), Box::new(self.unary()?));
+        }
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> Result<Expr> {
+        if self.eat(&TokenKind::Not) {
+            Ok(Expr::Not(Box::new(self.unary()?)))
+        } else {
+            self.primary()
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr> {
+        let token = self.next();
+        match token.map(|token| &token.kind) {
+            Some(TokenKind::LParen) => {
+                let expr = self.expr()?;
+                if !self.eat(&TokenKind::RParen) {
+                    return Err(self.unexpected(self.peek(), "`)`"));
+                }
+                Ok(expr)
+            }
+            Some(TokenKind::Ident(field)) => {
+                let start = token.expect("matched above").start;
+                let kind = Animal::field_kind(field).ok_or_else(|| {
+                    self.error_at(
+                        start,
+                        format!(
+                            "unknown field `{}`, expected one of {}",
+                            field,
+                            Animal::FIELDS.join(", ")
+                        ),
+                    )
+                })?;
+                self.comparison(field, kind)
+            }
+            _ => Err(self.unexpected(token, "a field name or `(`")),
+        }
+    }
+
+    fn comparison(&mut self, field: &str, kind: FieldKind) -> Result<Expr> {
+        let op = match self.peek().map(|token| &token.kind) {
+            Some(TokenKind::Op(op)) => *op,
+            _ if kind == FieldKind::Bool => return Ok(Expr::Flag(field.to_string())),
+            _ => {
+                return Err(self.unexpected(
+                    self.peek(),
+                    &format!("a comparison for {} field `{}`", kind, field),
+                ))
+            }
+        };
+        let op_token = self.next().expect("peeked above");
+
+        if op.is_ordering() && !matches!(kind, FieldKind::Number | FieldKind::Date) {
+            return Err(self.error_at(
+                op_token.start,
+                format!(
+                    "`{}` can only compare numbers and dates, but `{}` is a {} field",
+                    op.as_str(),
+                    field,
+                    kind
+                ),
+            ));
+        }
+
+        let value_token = self.next();
+        let value = match (kind, value_token.map(|token| &token.kind)) {
+            (FieldKind::Number, Some(TokenKind::Number(number))) => Literal::Number(*number),
+            (FieldKind::Text, Some(TokenKind::Text(text)))
+            | (FieldKind::Text, Some(TokenKind::Ident(text))) => Literal::Text(text.clone()),
+            (FieldKind::Text, Some(TokenKind::Number(number))) => Literal::Text(number.to_string()),
+            (FieldKind::Date, Some(TokenKind::Text(text))) => {
+                match NaiveDate::parse_from_str(text, "%Y-%m-%d") {
+                    Ok(date) => Literal::Date(date),
+                    Err(_) => {
+                        let token = value_token.expect("matched above");
+                        return Err(self.error_at(
+                            token.start,
+                            format!("invalid date \"{}\", expected \"YYYY-MM-DD\"", text),
+                        ));
+                    }
+                }
+            }
+            (FieldKind::Bool, Some(TokenKind::Ident(ident))) if ident == "true" => {
+                Literal::Bool(true)
+            }
+            (FieldKind::Bool, Some(TokenKind::Ident(ident))) if ident == "false" => {
+                Literal::Bool(false)
+            }
+            (_, Some(_)) => {
+                let token = value_token.expect("matched above");
+                return Err(self.error_at(
+                    token.start,
+                    format!(
+                        "cannot compare {} field `{}` with {}",
+                        kind, field, token.kind
+                    ),
+                ));
+            }
+            (_, None) => {
+                return Err(self.unexpected(None, &format!("a value after `{}`", op.as_str())))
+            }
+        };
+
+        Ok(Expr::Compare {
+            field: field.to_string(),
+            op,
+            value,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn animals() -> Vec<Animal> {
+        vec![
+            Animal {
+                name: String::from("Sam"),
+                species: String::from("Dog"),
+                strength: 12,
+                carnivore: true,
+                ..Animal::default()
+            },
+            Animal {
+                name: String::from("Simba"),
+                species: String::from("Lion"),
+                strength: 150,
+                carnivore: true,
+                arrival: NaiveDate::from_ymd_opt(2024, 5, 1),
+                ..Animal::default()
+            },
+            Animal {
+                name: String::from("Kevin"),
+                species: String::from("Gorrilla"),
+                strength: 50,
+                carnivore: false,
+                ..Animal::default()
+            },
+        ]
+    }
+
+    fn names(filter: &str) -> Vec<String> {
+        Filter::parse(filter)
+            .unwrap()
+            .apply(animals())
+            .into_iter()
+            .map(|animal| animal.name)
+            .collect()
+    }
+
+    #[test]
+    fn filter_expressions() {
+        assert_eq!(names("carnivore && strength > 20"), ["Simba"]);
+        assert_eq!(names("carnivore && species != Dog"), ["Simba"]);
+        assert_eq!(
+            names("species == \" lion \" || species == gorrilla"),
+            ["Simba", "Kevin"]
+        );
+        assert_eq!(names("carnivore && species != dog"), ["Simba"]);
+        assert!(names("name == sam").is_empty());
+        assert_eq!(names("!carnivore || name == \"Sam\""), ["Sam", "Kevin"]);
+        assert_eq!(names("(strength >= 50) && carnivore == false"), ["Kevin"]);
+        assert_eq!(names("strength<=12||strength=150"), ["Sam", "Simba"]);
+        assert_eq!(names("arrival >= '2024-05-01'"), ["Simba"]);
+        assert!(names("arrival < '2024-05-01'").is_empty());
+    }
+
+    #[test]
+    fn filter_parse_errors() {
+        let position = |filter: &str| match Filter::parse(filter) {
+            Err(Error::Filter { position, .. }) => position,
+            other => panic!("expected a filter error, got {:?}", other),
+        };
+
+        assert_eq!(position("carnivore && strength >"), 23);
+        assert_eq!(position("legs > 2"), 0);
+        assert_eq!(position("species > Dog"), 8);
+        assert_eq!(position("strength > Dog"), 11);
+        assert_eq!(position("(carnivore"), 10);
+        assert_eq!(position("carnivore & strength"), 10);
+        assert_eq!(position("name == \"Sam"), 8);
+        assert_eq!(position("carnivore strength"), 10);
+        assert_eq!(position("arrival > '2024-13-01'"), 10);
+    }
+}
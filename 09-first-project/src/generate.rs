@@ -0,0 +1,318 @@
+//! Synthetic inventories for load tests and property tests.
+
+use crate::error::Error;
+use crate::rng::Rng;
+use crate::{Animal, Result};
+use std::str::FromStr;
+
+/// `Distribution` describes how the strengths of the species are distributed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Distribution {
+    /// Every strength in `min..=max` is equally likely
+    Uniform { min: usize, max: usize },
+    /// Every order of magnitude in `min..=max` is equally likely, so there are
+    /// a few very strong species, like in `animals.json`
+    LogUniform { min: usize, max: usize },
+    /// Normally distributed strengths, cut off at 0
+    Normal { mean: f64, std_dev: f64 },
+}
+
+/// `Shape` selects the kind of inventory that is generated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Shape {
+    /// Every species gets a strength from the `Distribution`
+    Realistic,
+    /// All carnivores are equally strong, so they mustn't eat each other
+    /// (rule 5)
+    EqualCarnivores,
+    /// Every herbivore species is within one strength point of a carnivore
+    /// species, so that `fitting_cage` has to get `<` right
+    NearMisses,
+}
+
+/// `Generator` generates an inventory of animals.
+///
+/// The same configuration and `seed` always generate the same inventory.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Generator {
+    pub seed: u64,
+    /// Number of species
+    pub species: usize,
+    /// Range of the number of animals per species
+    pub animals_per_species: (usize, usize),
+    /// Share of carnivore species in `0.0..=1.0`
+    pub carnivore_ratio: f64,
+    pub strength: Distribution,
+    /// How much the strength of an animal may differ from the strength of its
+    /// species, relative to the species strength
+    pub variation: f64,
+    pub shape: Shape,
+}
+
+impl Default for Generator {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            species: 10,
+            animals_per_species: (1, 3),
+            carnivore_ratio: 0.5,
+            strength: Distribution::LogUniform { min: 1, max: 10000 },
+            variation: 0.2,
+            shape: Shape::Realistic,
+        }
+    }
+}
+
+const SPECIES_PREFIXES: &[&str] = &[
+    "Red", "Striped", "Spotted", "Giant", "Pygmy", "Golden", "Snow", "Desert", "Marsh", "Crested",
+];
+const SPECIES_NAMES: &[&str] = &[
+    "Fox", "Lynx", "Otter", "Heron", "Bison", "Tapir", "Gecko", "Ibis", "Jackal", "Okapi",
+    "Ferret", "Panda", "Hyena", "Lemur", "Marten", "Quokka",
+];
+const ANIMAL_NAMES: &[&str] = &[
+    "Ada", "Bruno", "Cleo", "Dora", "Emil", "Fiona", "Gus", "Hilda", "Igor", "Juno", "Kiki",
+    "Lotte", "Milo", "Nina", "Otto", "Pia",
+];
+
+impl Generator {
+    /// `generate` generates the inventory.
+    pub fn generate(&self) -> Vec<Animal> {
+        let mut rng = Rng::new(self.seed);
+
+        // Decide which species are carnivores. The ratio is met exactly,
+        // instead of rolling the dice for every species.
+        let carnivores = (self.species as f64 * self.carnivore_ratio).round() as usize;
+        let mut diets: Vec<_> = (0..self.species).map(|i| i < carnivores).collect();
+        rng.shuffle(&mut diets);
+
+        let mut strengths: Vec<_> = (0..self.species)
+            .map(|_| self.strength.sample(&mut rng))
+            .collect();
+        match self.shape {
+            Shape::Realistic => {}
+            Shape::EqualCarnivores => {
+                let strength = self.strength.sample(&mut rng);
+                for (species, _) in diets.iter().enumerate().filter(|(_, &c)| c) {
+                    strengths[species] = strength;
+                }
+            }
+            Shape::NearMisses => {
+                let predators: Vec<_> = (0..self.species).filter(|&i| diets[i]).collect();
+                if !predators.is_empty() {
+                    for species in (0..self.species).filter(|&i| !diets[i]) {
+                        let predator = strengths[predators[rng.range(0, predators.len() - 1)]];
+                        strengths[species] =
+                            predator.saturating_add(rng.range(0, 2)).saturating_sub(1);
+                    }
+                }
+            }
+        }
+
+        let mut animals = vec![];
+        for species in 0..self.species {
+            let name = species_name(species);
+            let (min, max) = self.animals_per_species;
+            let count = rng.range(min, max.max(min));
+            for i in 0..count {
+                let strength = match self.shape {
+                    // Variation would destroy the carefully chosen strengths
+                    Shape::EqualCarnivores if diets[species] => strengths[species],
+                    Shape::NearMisses => strengths[species],
+                    _ => vary(&mut rng, strengths[species], self.variation),
+                };
+                animals.push(Animal {
+                    name: animal_name(i),
+                    species: name.clone(),
+                    strength,
+                    carnivore: diets[species],
+                    ..Animal::default()
+                });
+            }
+        }
+
+        rng.shuffle(&mut animals);
+        animals
+    }
+}
+
+impl Distribution {
+    fn sample(&self, rng: &mut Rng) -> usize {
+        match *self {
+            Self::Uniform { min, max } => rng.range(min, max),
+            Self::LogUniform { min, max } => {
+                let (low, high) = ((min.max(1) as f64).ln(), (max.max(1) as f64).ln());
+                let strength = (low + (high - low) * rng.next_f64()).exp().round() as usize;
+                strength.clamp(min, max)
+            }
+            Self::Normal { mean, std_dev } => rng.normal(mean, std_dev).round().max(0.0) as usize,
+        }
+    }
+}
+
+fn vary(rng: &mut Rng, strength: usize, variation: f64) -> usize {
+    let spread = (strength as f64 * variation).round() as usize;
+    rng.range(
+        strength.saturating_sub(spread),
+        strength.saturating_add(spread),
+    )
+}
+
+fn species_name(i: usize) -> String {
+    let name = SPECIES_NAMES[i % SPECIES_NAMES.len()];
+    let prefix = SPECIES_PREFIXES[(i / SPECIES_NAMES.len()) % SPECIES_PREFIXES.len()];
+    match i / (SPECIES_NAMES.len() * SPECIES_PREFIXES.len()) {
+        0 => format!("{} {}", prefix, name),
+        n => format!("{} {} {}", prefix, name, n + 1),
+    }
+}
+
+fn animal_name(i: usize) -> String {
+    match i / ANIMAL_NAMES.len() {
+        0 => ANIMAL_NAMES[i].to_string(),
+        n => format!("{} {}", ANIMAL_NAMES[i % ANIMAL_NAMES.len()], n + 1),
+    }
+}
+
+fn invalid(value: &str, expected: &str) -> Error {
+    Error::InvalidValue(format!("invalid value `{}`, expected {}", value, expected))
+}
+
+/// Parses `uniform:MIN:MAX`, `log:MIN:MAX` or `normal:MEAN:STD_DEV`.
+impl FromStr for Distribution {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let expected = "`uniform:MIN:MAX`, `log:MIN:MAX` or `normal:MEAN:STD_DEV`";
+        let parts: Vec<_> = s.split(':').collect();
+        let (kind, a, b) = match parts[..] {
+            [kind, a, b] => (kind, a, b),
+            _ => return Err(invalid(s, expected)),
+        };
+        let int = |v: &str| v.parse::<usize>().map_err(|_| invalid(s, expected));
+        let float = |v: &str| v.parse::<f64>().map_err(|_| invalid(s, expected));
+
+        let distribution = match kind {
+            "uniform" => Self::Uniform {
+                min: int(a)?,
+                max: int(b)?,
+            },
+            "log" => Self::LogUniform {
+                min: int(a)?,
+                max: int(b)?,
+            },
+            "normal" => Self::Normal {
+                mean: float(a)?,
+                std_dev: float(b)?,
+            },
+            _ => return Err(invalid(s, expected)),
+        };
+        match distribution {
+            Self::Uniform { min, max } | Self::LogUniform { min, max } if min > max => {
+                Err(invalid(s, "MIN <= MAX"))
+            }
+            Self::Normal { mean, std_dev }
+                if !mean.is_finite() || !std_dev.is_finite() || std_dev <= 0.0 =>
+            {
+                Err(invalid(s, "a finite MEAN and STD_DEV > 0"))
+            }
+            _ => Ok(distribution),
+        }
+    }
+}
+
+impl FromStr for Shape {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "realistic" => Ok(Self::Realistic),
+            "equal-carnivores" => Ok(Self::EqualCarnivores),
+            "near-misses" => Ok(Self::NearMisses),
+            _ => Err(invalid(
+                s,
+                "`realistic`, `equal-carnivores` or `near-misses`",
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn strongest_per_species(animals: &[Animal]) -> HashMap<&str, (usize, bool)> {
+        let mut species = HashMap::new();
+        for animal in animals {
+            let entry = species
+                .entry(animal.species.as_str())
+                .or_insert((0, animal.carnivore));
+            entry.0 = entry.0.max(animal.strength);
+        }
+        species
+    }
+
+    #[test]
+    fn generate_reproducible() {
+        let generator = Generator {
+            seed: 42,
+            ..Generator::default()
+        };
+
+        let animals = generator.generate();
+        let species = strongest_per_species(&animals);
+
+        assert_eq!(species.len(), 10);
+        assert_eq!(species.values().filter(|(_, c)| *c).count(), 5);
+        assert_eq!(
+            serde_json::to_string(&animals).unwrap(),
+            serde_json::to_string(&generator.generate()).unwrap()
+        );
+    }
+
+    #[test]
+    fn generate_adversarial() {
+        let equal = Generator {
+            shape: Shape::EqualCarnivores,
+            ..Generator::default()
+        }
+        .generate();
+        let mut strengths = equal.iter().filter(|a| a.carnivore).map(|a| a.strength);
+        let first = strengths.next().unwrap();
+        assert!(strengths.all(|strength| strength == first));
+
+        let near_misses = Generator {
+            shape: Shape::NearMisses,
+            ..Generator::default()
+        }
+        .generate();
+        let species = strongest_per_species(&near_misses);
+        for (strength, _) in species.values().filter(|(_, c)| !c) {
+            assert!(species
+                .values()
+                .any(|(predator, c)| *c && (*predator as i64 - *strength as i64).abs() <= 1));
+        }
+    }
+
+    #[test]
+    fn parse_distribution() {
+        assert_eq!(
+            "uniform:1:10".parse::<Distribution>().unwrap(),
+            Distribution::Uniform { min: 1, max: 10 }
+        );
+        assert_eq!(
+            "normal:50:12.5".parse::<Distribution>().unwrap(),
+            Distribution::Normal {
+                mean: 50.0,
+                std_dev: 12.5
+            }
+        );
+        assert!("log:10:1".parse::<Distribution>().is_err());
+        assert!("uniform:1".parse::<Distribution>().is_err());
+        assert!("normal:50:0".parse::<Distribution>().is_err());
+        assert!("normal:50:-1".parse::<Distribution>().is_err());
+        assert!("normal:50:NaN".parse::<Distribution>().is_err());
+        assert!("normal:inf:1".parse::<Distribution>().is_err());
+    }
+}
@@ -0,0 +1,206 @@
+//! Which animals are due for a check by the vet.
+//!
+//! The vet walks from cage to cage, so the `HealthReport` groups the animals
+//! that are due by the cage they currently live in: one of the cages of the
+//! layout, the quarantine or the isolation for contagious animals.
+
+use crate::caging::Layout;
+use crate::species::same_species;
+use crate::Animal;
+use chrono::{Duration, NaiveDate};
+use serde::Serialize;
+use std::fmt::{self, Display, Formatter};
+
+/// `Enclosure` is where an animal currently lives.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Enclosure {
+    /// The position of the cage in the layout, starting at 1
+    Cage(usize),
+    Quarantine,
+    Isolation,
+}
+
+/// `Checkup` is an animal that is due for a check.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct Checkup {
+    pub animal: Animal,
+    pub due: NaiveDate,
+}
+
+/// `HealthReport` lists the checkups due within some days, grouped by
+/// `Enclosure`.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct HealthReport {
+    pub today: NaiveDate,
+    /// The enclosures with at least one checkup, ordered like the layout,
+    /// and their checkups, ordered by the day they are due
+    pub enclosures: Vec<(Enclosure, Vec<Checkup>)>,
+}
+
+impl Enclosure {
+    /// `of` finds the enclosure of `animal` in the `layout`, by its name and
+    /// species.
+    pub fn of(layout: &Layout, animal: &Animal) -> Option<Self> {
+        let same = |other: &Animal| {
+            other.name == animal.name && same_species(&other.species, &animal.species)
+        };
+        if let Some(i) = layout
+            .cages
+            .iter()
+            .position(|cage| cage.animals.iter().any(same))
+        {
+            Some(Self::Cage(i + 1))
+        } else if layout.quarantine.iter().any(|cage| same(&cage.animal)) {
+            Some(Self::Quarantine)
+        } else if layout.isolation.iter().any(same) {
+            Some(Self::Isolation)
+        } else {
+            None
+        }
+    }
+}
+
+impl HealthReport {
+    /// `new` finds the animals of the `layout` that are due for a check
+    /// within `days` after `today`, including overdue ones.
+    pub fn new(layout: &Layout, today: NaiveDate, days: i64) -> Self {
+        let until = today + Duration::days(days);
+        let cages = layout.cages.iter().enumerate().flat_map(|(i, cage)| {
+            cage.animals
+                .iter()
+                .map(move |a| (Enclosure::Cage(i + 1), a))
+        });
+        let quarantine = layout
+            .quarantine
+            .iter()
+            .map(|cage| (Enclosure::Quarantine, &cage.animal));
+        let isolation = layout
+            .isolation
+            .iter()
+            .map(|animal| (Enclosure::Isolation, animal));
+
+        let mut enclosures: Vec<(Enclosure, Vec<Checkup>)> = vec![];
+        for (enclosure, animal) in cages.chain(quarantine).chain(isolation) {
+            let due = match animal.vet_check() {
+                Some(due) if due <= until => due,
+                _ => continue,
+            };
+            let checkup = Checkup {
+                animal: animal.clone(),
+                due,
+            };
+            match enclosures.last_mut() {
+                Some((last, checkups)) if *last == enclosure => checkups.push(checkup),
+                _ => enclosures.push((enclosure, vec![checkup])),
+            }
+        }
+        for (_, checkups) in &mut enclosures {
+            checkups.sort_by_key(|checkup| checkup.due);
+        }
+
+        Self { today, enclosures }
+    }
+}
+
+impl Display for Enclosure {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Cage(cage) => write!(f, "Cage {}", cage),
+            Self::Quarantine => write!(f, "Quarantine"),
+            Self::Isolation => write!(f, "Isolation"),
+        }
+    }
+}
+
+impl Display for HealthReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.enclosures.is_empty() {
+            return writeln!(f, "No animal is due for a check.");
+        }
+        for (enclosure, checkups) in &self.enclosures {
+            writeln!(f, "{}:", enclosure)?;
+            for Checkup { animal, due } in checkups {
+                let when = match *due < self.today {
+                    true => "overdue since",
+                    false => "due",
+                };
+                write!(
+                    f,
+                    "  - {} the {}: {} {}",
+                    animal.name, animal.species, when, due
+                )?;
+
+                let mut conditions = vec![];
+                if animal.is_injured() {
+                    conditions.push(String::from("injured"));
+                }
+                if animal.is_contagious() {
+                    conditions.push(String::from("contagious"));
+                }
+                if let Some(medication) = animal.health.as_ref().and_then(|h| h.medication.as_ref())
+                {
+                    conditions.push(format!("on {}", medication));
+                }
+                if !conditions.is_empty() {
+                    write!(f, " ({})", conditions.join(", "))?;
+                }
+                writeln!(f)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::animal::animal;
+    use crate::caging::Caging;
+    use crate::Health;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 5, day).unwrap()
+    }
+
+    #[test]
+    fn report() {
+        let due = |name: &str, day: u32, contagious: bool| {
+            let mut animal = animal(name, "Zebra", 10, false);
+            animal.health = Some(Health {
+                contagious,
+                medication: Some(String::from("antibiotics")),
+                vet_check: Some(date(day)),
+                ..Health::default()
+            });
+            animal
+        };
+        let animals = vec![
+            due("Marty", 9, false),
+            due("Stripes", 2, false),
+            due("Zig", 30, false),
+            due("Gloria", 5, true),
+        ];
+        let layout = Caging::default().cage(animals, None).unwrap();
+
+        let report = HealthReport::new(&layout, date(3), 7);
+
+        let names: Vec<(Enclosure, Vec<&str>)> = report
+            .enclosures
+            .iter()
+            .map(|(enclosure, checkups)| {
+                let names = checkups.iter().map(|c| c.animal.name.as_str());
+                (*enclosure, names.collect())
+            })
+            .collect();
+        assert_eq!(
+            names,
+            [
+                (Enclosure::Cage(1), vec!["Stripes", "Marty"]),
+                (Enclosure::Isolation, vec!["Gloria"]),
+            ]
+        );
+        assert!(report
+            .to_string()
+            .contains("  - Stripes the Zebra: overdue since 2024-05-02 (on antibiotics)\n"));
+    }
+}
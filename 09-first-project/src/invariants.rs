@@ -0,0 +1,285 @@
+//! Invariants every cage layout produced by `cage_em_all` has to satisfy.
+//!
+//! These are the rules from the README, checked after the fact. This is used
+//! by the property tests, but can also be used to validate layouts that were
+//! edited by hand.
+//!
+//! The rule that every species lives in one cage has an exception: `Caging`
+//! splits species whose social requirements keep their animals apart, e.g.
+//! solitary tigers. `check_with` accepts these splits, `check` doesn't.
+
+use crate::caging::Caging;
+use crate::species::{normalize, same_species};
+use crate::{Animal, Cage};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::{self, Display, Formatter};
+
+/// `Violation` is a broken invariant of a cage layout.
+///
+/// Cages are identified by their position in the layout, starting at 1.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub enum Violation {
+    /// An animal of the inventory isn't in any cage
+    Missing(Animal),
+    /// An animal is in more than one cage, or multiple times in one cage
+    Duplicated { animal: Animal, count: usize },
+    /// An animal in a cage isn't part of the inventory
+    Unknown(Animal),
+    /// A cage without animals
+    EmptyCage(usize),
+    /// The animals of a species were put into different cages, without their
+    /// social requirements asking for it
+    SplitSpecies { species: String, cages: Vec<usize> },
+    /// A cage contains carnivores of more than one species
+    CarnivoreSpecies { cage: usize, species: Vec<String> },
+    /// A predator can eat one of its cagemates
+    Predation {
+        cage: usize,
+        predator: Animal,
+        prey: Animal,
+    },
+    /// A food animal isn't in the cage of a strongest carnivore
+    FoodMisplaced { food: Animal, cage: usize },
+    /// A food animal is at least as strong as an animal it is fed to
+    FoodTooStrong {
+        cage: usize,
+        food: Animal,
+        animal: Animal,
+    },
+}
+
+/// `check` checks that `cages` is a valid layout for `animals`, when the
+/// `food` species is fed to the strongest carnivore.
+///
+/// Animals are identified by their name and species. An empty result means
+/// that the layout is valid.
+pub fn check(animals: &[Animal], food: Option<&str>, cages: &[Cage]) -> Vec<Violation> {
+    check_layout(animals, food, cages, |_, _| false)
+}
+
+/// `check_with` is `check` for the cages of a layout made by `caging`.
+///
+/// A species may be split across cages, as long as every two of its cages
+/// hold animals that mustn't live together, see `Social`. The `animals` are
+/// the ones that were caged, without quarantine and isolation.
+///
+/// [`Social`]: crate::catalogue::Social
+pub fn check_with(
+    caging: &Caging,
+    animals: &[Animal],
+    food: Option<&str>,
+    cages: &[Cage],
+) -> Vec<Violation> {
+    check_layout(animals, food, cages, |a, b| caging.conflict(a, b))
+}
+
+/// Checks the layout, where `conflict` tells if two animals of the same
+/// species mustn't share a cage, which allows to split their species.
+fn check_layout(
+    animals: &[Animal],
+    food: Option<&str>,
+    cages: &[Cage],
+    conflict: impl Fn(&Animal, &Animal) -> bool,
+) -> Vec<Violation> {
+    let is_food = |animal: &Animal| food.is_some_and(|food| same_species(&animal.species, food));
+    let mut violations = vec![];
+
+    // Every animal of the inventory is in exactly one cage
+    let mut counts: HashMap<(&str, &str), (&Animal, usize)> = HashMap::new();
+    for cage in cages {
+        for animal in &cage.animals {
+            counts.entry(key(animal)).or_insert((animal, 0)).1 += 1;
+        }
+    }
+    for animal in animals {
+        match counts.remove(&key(animal)) {
+            None => violations.push(Violation::Missing(animal.clone())),
+            Some((_, 1)) => {}
+            Some((_, count)) => violations.push(Violation::Duplicated {
+                animal: animal.clone(),
+                count,
+            }),
+        }
+    }
+    let mut unknown: Vec<_> = counts.into_iter().map(|(_, (animal, _))| animal).collect();
+    unknown.sort_by_key(|animal| key(animal));
+    violations.extend(unknown.into_iter().cloned().map(Violation::Unknown));
+
+    // Every species lives in a single cage, unless its animals have to be
+    // kept apart
+    let mut species_cages: BTreeMap<String, (&str, BTreeMap<usize, Vec<&Animal>>)> =
+        BTreeMap::new();
+    for (i, cage) in cages.iter().enumerate() {
+        if cage.animals.is_empty() {
+            violations.push(Violation::EmptyCage(i + 1));
+        }
+        for animal in &cage.animals {
+            species_cages
+                .entry(normalize(&animal.species))
+                .or_insert((&animal.species, BTreeMap::new()))
+                .1
+                .entry(i + 1)
+                .or_default()
+                .push(animal);
+        }
+    }
+    for (species, cages) in species_cages.into_values() {
+        let groups: Vec<_> = cages.values().collect();
+        let apart = groups.iter().enumerate().all(|(i, a)| {
+            groups[i + 1..]
+                .iter()
+                .all(|b| a.iter().any(|a| b.iter().any(|b| conflict(a, b))))
+        });
+        if !apart {
+            violations.push(Violation::SplitSpecies {
+                species: species.to_string(),
+                cages: cages.into_keys().collect(),
+            });
+        }
+    }
+
+    for (i, cage) in cages.iter().enumerate() {
+        // Food is supposed to be eaten, so it doesn't count here
+        let residents: Vec<_> = cage.animals.iter().filter(|a| !is_food(a)).collect();
+
+        let carnivore_species: BTreeSet<_> = residents
+            .iter()
+            .filter(|animal| animal.carnivore)
+            .map(|animal| normalize(&animal.species))
+            .collect();
+        if carnivore_species.len() > 1 {
+            violations.push(Violation::CarnivoreSpecies {
+                cage: i + 1,
+                species: carnivore_species.into_iter().collect(),
+            });
+        }
+
+        for predator in residents.iter().filter(|animal| animal.carnivore) {
+            for prey in &residents {
+                if eats(predator, prey) {
+                    violations.push(Violation::Predation {
+                        cage: i + 1,
+                        predator: (*predator).clone(),
+                        prey: (*prey).clone(),
+                    });
+                }
+            }
+        }
+    }
+
+    if food.is_some() {
+        check_food(animals, cages, is_food, &mut violations);
+    }
+
+    violations
+}
+
+/// Rule 4 of the README: `predator` eats `prey` if it is a stronger carnivore
+/// of a different species. Injured prey counts with its `effective_strength`.
+pub fn eats(predator: &Animal, prey: &Animal) -> bool {
+    predator.carnivore
+        && predator.strength > prey.effective_strength()
+        && !same_species(&predator.species, &prey.species)
+}
+
+fn check_food(
+    animals: &[Animal],
+    cages: &[Cage],
+    is_food: impl Fn(&Animal) -> bool,
+    violations: &mut Vec<Violation>,
+) {
+    // If several carnivores are equally strong, any of their cages will do
+    let strongest = animals
+        .iter()
+        .filter(|animal| animal.carnivore && !is_food(animal))
+        .map(|animal| animal.strength)
+        .max();
+    let is_feeding_cage = |cage: &Cage| {
+        cage.animals.iter().any(|animal| {
+            animal.carnivore && !is_food(animal) && Some(animal.strength) == strongest
+        })
+    };
+
+    for (i, cage) in cages.iter().enumerate() {
+        for food in cage.animals.iter().filter(|animal| is_food(animal)) {
+            if !is_feeding_cage(cage) {
+                violations.push(Violation::FoodMisplaced {
+                    food: food.clone(),
+                    cage: i + 1,
+                });
+                continue;
+            }
+            let weakest = cage.animals.iter().filter(|animal| !is_food(animal)).min();
+            if let Some(weakest) = weakest.filter(|weakest| food.strength >= weakest.strength) {
+                violations.push(Violation::FoodTooStrong {
+                    cage: i + 1,
+                    food: food.clone(),
+                    animal: weakest.clone(),
+                });
+            }
+        }
+    }
+}
+
+fn key(animal: &Animal) -> (&str, &str) {
+    (&animal.name, &animal.species)
+}
+
+struct Named<'a>(&'a Animal);
+
+impl Display for Named<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} the {} ({})",
+            self.0.name, self.0.species, self.0.strength
+        )
+    }
+}
+
+impl Display for Violation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Missing(animal) => write!(f, "{} is not in any cage", Named(animal)),
+            Self::Duplicated { animal, count } => {
+                write!(f, "{} is caged {} times", Named(animal), count)
+            }
+            Self::Unknown(animal) => write!(f, "{} is not in the inventory", Named(animal)),
+            Self::EmptyCage(cage) => write!(f, "cage {} is empty", cage),
+            Self::SplitSpecies { species, cages } => {
+                write!(f, "species {} is split across cages {:?}", species, cages)
+            }
+            Self::CarnivoreSpecies { cage, species } => write!(
+                f,
+                "cage {} contains carnivores of multiple species: {}",
+                cage,
+                species.join(", ")
+            ),
+            Self::Predation {
+                cage,
+                predator,
+                prey,
+            } => write!(
+                f,
+                "{} can eat {} in cage {}",
+                Named(predator),
+                Named(prey),
+                cage
+            ),
+            Self::FoodMisplaced { food, cage } => write!(
+                f,
+                "food {} is in cage {}, not with a strongest carnivore",
+                Named(food),
+                cage
+            ),
+            Self::FoodTooStrong { cage, food, animal } => write!(
+                f,
+                "food {} is not weaker than {} in cage {}",
+                Named(food),
+                Named(animal),
+                cage
+            ),
+        }
+    }
+}
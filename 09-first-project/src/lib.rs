@@ -1,12 +1,38 @@
 mod animal;
 mod cage;
-mod error;
-
+pub mod caging;
+pub mod catalogue;
+pub mod config;
+pub mod consistency;
+pub mod cost;
+pub mod diagnose;
+pub mod diff;
+pub mod error;
+pub mod federation;
+pub mod feeding;
+mod filter;
+pub mod generate;
+pub mod health;
+pub mod invariants;
+pub mod map;
+pub mod placement;
+pub mod risk;
+mod rng;
+pub mod simulation;
+pub mod species;
+pub mod staffing;
+pub mod stats;
+pub mod transfer;
+
+use consistency::Consistency;
+use placement::Placement;
 use std::cmp::Reverse;
+use std::collections::HashMap;
 
-pub use animal::Animal;
-pub use cage::Cage;
-pub use error::Result;
+pub use animal::{Animal, BreedingStatus, Health, Sex, INJURED_STRENGTH_PERCENT};
+pub use cage::{Cage, CageKind};
+pub use error::{Error, Result};
+pub use filter::Filter;
 
 /// `animal_groups` will take an iterator an group animals by species into cages.
 ///
@@ -23,8 +49,28 @@ pub use error::Result;
 /// basically means that this function takes some `Iterator` which you can then
 /// use in e.g. a `for`-loop:
 fn animal_groups<'a>(animals: impl Iterator<Item = &'a Animal>) -> Vec<Cage> {
-    let _ = animals; // suppress warning
-    vec![]
+    // Remember the order in which the species first appeared, so that the
+    // resulting cages are deterministic.
+    let mut species_order = Vec::new();
+    let mut groups: HashMap<String, Vec<Animal>> = HashMap::new();
+
+    for animal in animals {
+        // Different spellings of a species still go into the same cage
+        let species = species::normalize(&animal.species);
+        groups
+            .entry(species.clone())
+            .or_insert_with(|| {
+                species_order.push(species);
+                vec![]
+            })
+            .push(animal.clone());
+    }
+
+    species_order
+        .into_iter()
+        .filter_map(|species| groups.remove(&species))
+        .map(Cage::from)
+        .collect()
 }
 
 /// `sorted_animal_groups` does the same as (and can reuse) `animal_groups`.
@@ -37,20 +83,33 @@ where
     F: Fn(&Cage) -> K,
     K: Ord,
 {
-    let _ = animals; // suppress warning
-    let _ = key; // suppress warning
-    vec![]
+    let mut groups = animal_groups(animals);
+    groups.sort_by_key(key);
+    groups
 }
 
-/// `fitting_cage` tries to `find` a fitting cage for the passed herbivore.
+/// `fitting_cage` tries to `find` a fitting cage for the passed group of
+/// herbivores.
+///
+/// The most vulnerable herbivore has to be safe in the cage, and the cage has
+/// to provide the habitat every herbivore needs. Only the cages that are
+/// `open` are considered. If several cages qualify, the `placement` decides.
 ///
 /// # Hint
 ///
 /// With the use of iterators, this is a one-liner
-fn fitting_cage<'a>(cages: &'a mut [Cage], herbivore: &Animal) -> Option<&'a mut Cage> {
+fn fitting_cage<'a>(
+    cages: &'a mut [Cage],
+    herbivores: &Cage,
+    placement: &Placement,
+    open: impl Fn(usize) -> bool,
+) -> Option<&'a mut Cage> {
+    let herbivore = herbivores.most_vulnerable()?;
     assert!(!herbivore.carnivore);
-    let _ = cages; // suppress warning
-    None
+    let i = placement.choose(cages, herbivore, |i, cage| {
+        open(i) && cage.fits(herbivore) && cage.suits_all(herbivores)
+    })?;
+    Some(&mut cages[i])
 }
 
 /// BONUS: `extract_food` tries to extract the food animals from the passed animals.
@@ -63,14 +122,28 @@ fn fitting_cage<'a>(cages: &'a mut [Cage], herbivore: &Animal) -> Option<&'a mut
 /// # Errors
 ///
 /// This function errors if there are no `animals` of the `food` species.
-/// (If a function returns `Result`, you should put an `# Errors` section in
-/// the documentation)
 fn extract_food(
     animals: Vec<Animal>,
     food: Option<&str>,
 ) -> Result<(Option<Vec<Animal>>, Vec<Animal>)> {
-    let _ = food; // suppress warning
-    Ok((None, animals))
+    let food = match food {
+        Some(food) => food,
+        None => return Ok((None, animals)),
+    };
+
+    let (food_animals, animals): (Vec<_>, Vec<_>) = animals
+        .into_iter()
+        .partition(|animal| species::same_species(&animal.species, food));
+
+    if food_animals.is_empty() {
+        let suggestion = species::suggest(food, animals.iter().map(|a| a.species.as_str()));
+        return Err(Error::NoFood {
+            species: food.to_string(),
+            suggestion: suggestion.map(str::to_string),
+        });
+    }
+
+    Ok((Some(food_animals), animals))
 }
 
 // You won't have to modify the following functions. They should Just Work™.
@@ -81,7 +154,54 @@ fn extract_food(
 ///
 /// But you'll have to fill out the other function stubs in this files and the
 /// other modules for this to work.
+///
+/// # Errors
+///
+/// This function errors if the records of a species disagree on its diet,
+/// or if the `food` can't be fed to the strongest carnivore.
 pub fn cage_em_all(animals: Vec<Animal>, food: Option<&str>) -> Result<Vec<Cage>> {
+    cage_em_all_with(animals, food, &Placement::default())
+}
+
+/// `cage_em_all_with` works like `cage_em_all`, but lets the `placement`
+/// decide which cage a herbivore goes into, instead of rule 6.
+///
+/// # Errors
+///
+/// This function errors in the same cases as `cage_em_all`.
+pub fn cage_em_all_with(
+    animals: Vec<Animal>,
+    food: Option<&str>,
+    placement: &Placement,
+) -> Result<Vec<Cage>> {
+    let (carnivore_groups, herbivore_groups) = group_animals(animals, food)?;
+
+    Ok(relocate_animals(
+        carnivore_groups,
+        herbivore_groups,
+        placement,
+        |_| true,
+    ))
+}
+
+/// `group_animals` is the first half of `cage_em_all`: it groups the animals
+/// into carnivore cages, with the food delivered, and herbivore groups, which
+/// still have to be relocated.
+///
+/// # Errors
+///
+/// This function errors in the same cases as `cage_em_all`.
+pub(crate) fn group_animals(
+    animals: Vec<Animal>,
+    food: Option<&str>,
+) -> Result<(Vec<Cage>, Vec<Cage>)> {
+    // A species with conflicting diets would be split across two cages. Use
+    // `Consistency::resolve` beforehand to resolve such conflicts.
+    let conflicts = Consistency::default().check(&animals);
+    if !conflicts.is_empty() {
+        return Err(Error::Inconsistent(conflicts));
+    }
+
     let (food_animals, filtered_animals) = extract_food(animals, food)?;
 
     let mut carnivore_groups = sorted_animal_groups(
@@ -99,33 +219,46 @@ pub fn cage_em_all(animals: Vec<Animal>, food: Option<&str>) -> Result<Vec<Cage>
         animal_groups(filtered_animals.iter().filter(|animal| !animal.carnivore));
 
     if let Some(food_animals) = food_animals {
-        carnivore_groups[0].deliver_food(Cage::from(food_animals))?;
+        carnivore_groups
+            .first_mut()
+            .ok_or_else(|| Error::NoCarnivore(food.expect("food was extracted").to_string()))?
+            .deliver_food(Cage::from(food_animals))?;
     }
 
-    Ok(relocate_animals(carnivore_groups, herbivore_groups))
+    Ok((carnivore_groups, herbivore_groups))
 }
 
 /// Nothing to change here.
 ///
 /// `relocate_animals` takes the split up carnivores and herbivores and puts
 /// them in the correct cages.
-fn relocate_animals(mut carnivore_cages: Vec<Cage>, herbivore_cages: Vec<Cage>) -> Vec<Cage> {
-    let mut weak_herbivores = Cage::default();
+///
+/// Herbivores only join the carnivore cages for which `open` is true, given
+/// the index of the cage. The cost optimisation uses this to keep some
+/// carnivores on their own.
+pub(crate) fn relocate_animals(
+    mut carnivore_cages: Vec<Cage>,
+    herbivore_cages: Vec<Cage>,
+    placement: &Placement,
+    open: impl Fn(usize) -> bool,
+) -> Vec<Cage> {
+    // The herbivores that fit no carnivore cage, one cage per habitat
+    let mut weak_herbivores: Vec<Cage> = vec![];
 
     for herbivores in herbivore_cages {
-        let weakest_herbivore = herbivores
-            .weakest()
-            .expect("contains at least one herbivore");
-
-        if let Some(cage) = fitting_cage(&mut carnivore_cages, weakest_herbivore) {
+        if let Some(cage) = fitting_cage(&mut carnivore_cages, &herbivores, placement, &open) {
+            cage.move_from(herbivores);
+        } else if let Some(cage) = weak_herbivores
+            .iter_mut()
+            .find(|cage| cage.suits_all(&herbivores))
+        {
             cage.move_from(herbivores);
         } else {
-            weak_herbivores.move_from(herbivores);
+            weak_herbivores.push(herbivores);
         }
     }
 
-    carnivore_cages.push(weak_herbivores);
-
+    carnivore_cages.extend(weak_herbivores);
     carnivore_cages
 }
 
@@ -140,35 +273,41 @@ mod test {
             species: String::from("S1"),
             strength: 10,
             carnivore: false,
+            ..Animal::default()
         };
         let a2 = Animal {
             name: String::from("A2"),
             species: String::from("S1"),
             strength: 11,
             carnivore: false,
+            ..Animal::default()
         };
         let b1 = Animal {
             name: String::from("B1"),
             species: String::from("S2"),
             strength: 5,
             carnivore: true,
+            ..Animal::default()
         };
         let b2 = Animal {
             name: String::from("B2"),
             species: String::from("S2"),
             strength: 6,
             carnivore: true,
+            ..Animal::default()
         };
 
-        let animals = vec![a1.clone(), a2.clone(), b1.clone(), b2.clone()];
+        let animals = [a1.clone(), a2.clone(), b1.clone(), b2.clone()];
 
         let grouping = animal_groups(animals.iter());
 
         let cage1 = Cage {
             animals: vec![a1, a2],
+            ..Cage::default()
         };
         let cage2 = Cage {
             animals: vec![b1, b2],
+            ..Cage::default()
         };
 
         assert_eq!(grouping, vec![cage1, cage2]);
@@ -181,27 +320,31 @@ mod test {
             species: String::from("S1"),
             strength: 9,
             carnivore: true,
+            ..Animal::default()
         };
         let a2 = Animal {
             name: String::from("A2"),
             species: String::from("S1"),
             strength: 11,
             carnivore: true,
+            ..Animal::default()
         };
         let b1 = Animal {
             name: String::from("B1"),
             species: String::from("S2"),
             strength: 10,
             carnivore: true,
+            ..Animal::default()
         };
         let b2 = Animal {
             name: String::from("B2"),
             species: String::from("S2"),
             strength: 8,
             carnivore: true,
+            ..Animal::default()
         };
 
-        let animals = vec![a1.clone(), a2.clone(), b1.clone(), b2.clone()];
+        let animals = [a1.clone(), a2.clone(), b1.clone(), b2.clone()];
 
         // Sort from weak to strong
         let grouping =
@@ -209,9 +352,11 @@ mod test {
 
         let cage1 = Cage {
             animals: vec![a1, a2],
+            ..Cage::default()
         };
         let cage2 = Cage {
             animals: vec![b1, b2],
+            ..Cage::default()
         };
 
         assert_eq!(grouping, vec![cage2, cage1]);
@@ -224,39 +369,64 @@ mod test {
             species: String::from("S1"),
             strength: 9,
             carnivore: true,
+            ..Animal::default()
         };
         let a2 = Animal {
             name: String::from("A2"),
             species: String::from("S1"),
             strength: 11,
             carnivore: true,
+            ..Animal::default()
         };
         let b1 = Animal {
             name: String::from("B1"),
             species: String::from("S2"),
             strength: 10,
             carnivore: false,
+            ..Animal::default()
         };
         let b2 = Animal {
             name: String::from("B2"),
             species: String::from("S2"),
             strength: 11,
             carnivore: false,
+            ..Animal::default()
         };
         let b3 = Animal {
             name: String::from("B3"),
             species: String::from("S2"),
             strength: 12,
             carnivore: false,
+            ..Animal::default()
         };
 
         let cage1 = Cage {
             animals: vec![a1, a2],
+            ..Cage::default()
         };
 
-        assert!(fitting_cage(&mut [cage1.clone()], &b1).is_none());
-        assert!(fitting_cage(&mut [cage1.clone()], &b2).is_none());
-        assert!(fitting_cage(&mut [cage1], &b3).is_some());
+        let fits = |cages: &mut [Cage], herbivore: &Animal| {
+            fitting_cage(
+                cages,
+                &Cage::from(vec![herbivore.clone()]),
+                &Placement::default(),
+                |_| true,
+            )
+            .is_some()
+        };
+        assert!(!fits(&mut [cage1.clone()], &b1));
+        assert!(!fits(&mut [cage1.clone()], &b2));
+        assert!(fits(&mut [cage1.clone()], &b3));
+
+        // Every herbivore of the group needs its habitat
+        let habitat = |animal: &Animal, habitat: &str| Animal {
+            habitat: Some(habitat.to_string()),
+            ..animal.clone()
+        };
+        let arctic = Cage::from(vec![habitat(&cage1.animals[0], "Arctic")]);
+        let group = Cage::from(vec![habitat(&b3, "arctic"), habitat(&b3, "desert")]);
+        assert!(fits(&mut [arctic.clone()], &habitat(&b3, "arctic")));
+        assert!(fitting_cage(&mut [arctic], &group, &Placement::default(), |_| true).is_none());
     }
 
     #[test]
@@ -266,12 +436,14 @@ mod test {
             species: String::from("S1"),
             strength: 10,
             carnivore: true,
+            ..Animal::default()
         };
         let b1 = Animal {
             name: String::from("B1"),
             species: String::from("S2"),
             strength: 9,
             carnivore: false,
+            ..Animal::default()
         };
 
         assert_eq!(
@@ -292,30 +464,34 @@ mod test {
             species: String::from("S1"),
             strength: 10,
             carnivore: true,
+            ..Animal::default()
         };
         let b1 = Animal {
             name: String::from("B1"),
             species: String::from("S2"),
             strength: 9,
             carnivore: false,
+            ..Animal::default()
         };
         let b2 = Animal {
             name: String::from("B2"),
             species: String::from("S2"),
             strength: 10,
             carnivore: false,
+            ..Animal::default()
         };
         let b3 = Animal {
             name: String::from("B3"),
             species: String::from("S2"),
             strength: 11,
             carnivore: false,
+            ..Animal::default()
         };
 
-        let mut cage1 = Cage { animals: vec![a1] };
-        let food_cage1 = Cage { animals: vec![b1] };
-        let food_cage2 = Cage { animals: vec![b2] };
-        let food_cage3 = Cage { animals: vec![b3] };
+        let mut cage1 = Cage::from(vec![a1]);
+        let food_cage1 = Cage::from(vec![b1]);
+        let food_cage2 = Cage::from(vec![b2]);
+        let food_cage3 = Cage::from(vec![b3]);
 
         assert!(cage1.deliver_food(food_cage1).is_ok());
         assert!(cage1.deliver_food(food_cage2).is_err());
@@ -1,63 +1,1604 @@
-//! Nothing to change here.
+//! The `my-zoo` command line tool.
 //!
-//! Binary for your Zoo.
+//! Every feature of the library is a subcommand, see `app`. Without one, `cage`
+//! is run, so `my-zoo animals.json` still works. The options of a subcommand
+//! are looked up on the command line first, then in the `zoo.toml`
+//! configuration file, see `Settings`.
 //!
-//! This is just boilerplate for argument parsing, serilization, deserialization
-//! and reading/writing files.
+//! This module only parses the arguments, reads and writes the files and
+//! reports errors. The zoo itself is modelled in the library.
 
-use clap::{App, Arg};
+use clap::{value_t_or_exit, App, AppSettings, Arg, ArgMatches, ErrorKind, Shell, SubCommand};
+use std::collections::BTreeMap;
+use std::env;
+use std::ffi::OsString;
+use std::fmt::Display;
 use std::fs;
-use std::path::PathBuf;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::thread;
+use std::time::{Duration, SystemTime};
 
-use my_zoo::{cage_em_all, Animal, Result};
+use chrono::{Local, NaiveDate};
+use my_zoo::caging::{Caging, Layout, Quarantine, DEFAULT_QUARANTINE_DAYS};
+use my_zoo::catalogue::{self, Catalogue, Record};
+use my_zoo::config::{self, Config, Options};
+use my_zoo::consistency::Consistency;
+use my_zoo::cost::CostModel;
+use my_zoo::diagnose::diagnose;
+use my_zoo::diff::{LayoutDiff, Watch};
+use my_zoo::federation::{self, Goal, Site};
+use my_zoo::feeding::FeedingPlan;
+use my_zoo::generate::{Distribution, Generator, Shape};
+use my_zoo::health::HealthReport;
+use my_zoo::map::{self, Grid};
+use my_zoo::risk::RiskModel;
+use my_zoo::simulation::{self, Scenario};
+use my_zoo::species::{same_species, Registry};
+use my_zoo::staffing::Roster;
+use my_zoo::stats::{Stats, DEFAULT_GROWTH_SPURT};
+use my_zoo::transfer::{self, Transport};
+use my_zoo::{Animal, Cage, Error, Filter, Result, Sex};
+use serde::{Deserialize, Serialize};
+
+// Errors end up in `main`, which prints them like the warnings. If you want to
+// write a serious command line application, you should look at proper error
+// handling techniques in Rust. There are multiple crates out there, which
+// handle error better, than just throwing them at the user.
+fn main() {
+    match run() {
+        Ok(()) => {}
+        // E.g. `my-zoo schema | head`, there is no one left to tell
+        Err(Error::IOError(err)) if err.kind() == io::ErrorKind::BrokenPipe => {}
+        Err(err) => {
+            error(err);
+            process::exit(1);
+        }
+    }
+}
+
+fn run() -> Result<()> {
+    let growth_spurt = DEFAULT_GROWTH_SPURT.to_string();
 
-// Note that main can return a `Result`. If you want to write a serious command
-// line application, you should look at proper error handling techniques in
-// Rust. There are multiple crates out there, which handle error better, than
-// just throwing them at the user.
-fn main() -> Result<()> {
     // First parse the input from the command line
-    let matches = App::new("My Own Zoo")
+    let matches = app(&growth_spurt).get_matches_from(with_default_subcommand(env::args_os()));
+    let verbosity = match matches.is_present("quiet") {
+        true => 0,
+        false => 1 + matches.occurrences_of("verbose"),
+    };
+    VERBOSITY.store(verbosity, Ordering::Relaxed);
+    let color = match matches.value_of("color").unwrap() {
+        "always" => true,
+        "never" => false,
+        _ => io::stderr().is_terminal() && env::var_os("NO_COLOR").is_none(),
+    };
+    COLOR.store(color, Ordering::Relaxed);
+
+    // Options on the command line win over the configuration file
+    let config = match matches.value_of("config") {
+        Some(path) => Some((PathBuf::from(path), Config::load(Path::new(path))?)),
+        None => Config::discover(Path::new(""))?,
+    };
+    if let Some((path, _)) = &config {
+        info(format!("using the configuration file {}", path.display()));
+    }
+    let (command, matches) = match matches.subcommand() {
+        (command, Some(matches)) => (command, matches),
+        _ => unreachable!("a subcommand is required"),
+    };
+    let settings = Settings::new(command, matches, config.as_ref());
+
+    // Only the subcommands reading an inventory are configured
+    match command {
+        "add" => add(&settings),
+        "cage" => cage(&settings),
+        "completions" => completions(matches, app(&growth_spurt)),
+        "config" => show_config(matches, config.as_ref()),
+        "diff" => diff(matches),
+        "federate" => federate(&settings),
+        "generate" => generate(matches),
+        "health-report" => health_report(&settings),
+        "man" => man(matches, || app(&growth_spurt)),
+        "remove" => remove(&settings),
+        "schema" => schema(matches),
+        "simulate" => simulate(&settings),
+        "stats" => stats(&settings),
+        "transfer" => transfer(matches),
+        "validate" => validate(&settings),
+        "watch" => watch(&settings),
+        _ => unreachable!("unknown subcommand `{}`", command),
+    }
+}
+
+/// The subcommands of `app`.
+const SUBCOMMANDS: &[&str] = &[
+    "add",
+    "cage",
+    "completions",
+    "config",
+    "diff",
+    "federate",
+    "generate",
+    "health-report",
+    "man",
+    "remove",
+    "schema",
+    "simulate",
+    "stats",
+    "transfer",
+    "validate",
+    "watch",
+];
+
+/// The global options that take a value.
+const GLOBAL_OPTIONS: &[&str] = &["--config", "--format", "--output", "-o", "--color"];
+
+/// Before there were subcommands, `my-zoo animals.json` caged the animals,
+/// and it still does: `cage` is inserted, unless the first argument that
+/// isn't an option is a subcommand, or there is no argument at all.
+fn with_default_subcommand(args: impl Iterator<Item = OsString>) -> Vec<OsString> {
+    let mut args: Vec<OsString> = args.collect();
+    let mut rest = args.iter().skip(1).map(|arg| arg.to_string_lossy());
+    let explicit = loop {
+        match rest.next() {
+            None => break args.len() == 1,
+            Some(arg) if ["-h", "--help", "-V", "--version"].contains(&&*arg) => break true,
+            Some(arg) if GLOBAL_OPTIONS.contains(&&*arg) => {
+                rest.next();
+            }
+            Some(arg) if arg.starts_with('-') => {}
+            Some(arg) => break arg == "help" || SUBCOMMANDS.contains(&&*arg),
+        }
+    };
+    if !explicit {
+        args.insert(1, OsString::from("cage"));
+    }
+    args
+}
+
+fn app(growth_spurt: &str) -> App<'_, '_> {
+    App::new("my-zoo")
         .version("1.0")
+        .about("My Own Zoo: puts the animals of a zoo into cages, so that nobody gets eaten")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .global_setting(AppSettings::VersionlessSubcommands)
+        .arg(
+            Arg::with_name("config")
+                .long("config")
+                .value_name("FILE")
+                .help("read the options from FILE instead of zoo.toml")
+                .global(true)
+                .takes_value(true),
+        )
         .arg(
-            Arg::with_name("INPUT")
-                .required(true)
-                .help("input JSON file"),
+            Arg::with_name("format")
+                .long("format")
+                .value_name("FORMAT")
+                .possible_values(&["json", "toml"])
+                .help("format of the inventories, instead of guessing it from their extension")
+                .global(true)
+                .takes_value(true),
         )
         .arg(
-            Arg::with_name("food")
-                .long("food")
-                .short("f")
-                .help("specify an animal that should be used as food")
+            Arg::with_name("output")
+                .long("output")
+                .short("o")
+                .value_name("FILE")
+                .help("write what would be printed to FILE instead of stdout")
+                .global(true)
                 .takes_value(true),
         )
-        .get_matches();
-    let input = matches.value_of("INPUT").unwrap();
-    let food = matches.value_of("food");
+        .arg(
+            Arg::with_name("verbose")
+                .long("verbose")
+                .short("v")
+                .multiple(true)
+                .help("also print what is being done to stderr")
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("quiet")
+                .long("quiet")
+                .short("q")
+                .conflicts_with("verbose")
+                .help("don't print warnings, only errors")
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("color")
+                .long("color")
+                .value_name("WHEN")
+                .possible_values(&["auto", "always", "never"])
+                .help("color warnings and errors")
+                .default_value("auto")
+                .global(true),
+        )
+        .subcommand(
+            SubCommand::with_name("cage")
+                .about("put the animals in cages and write the layout, the default subcommand")
+                .args(&caging_args())
+                .arg(output_dir_arg())
+                .arg(Arg::with_name("feeding").long("feeding").help(
+                    "write a feeding plan as CSV and iCalendar file, with the diets of the catalogue",
+                ))
+                .arg(
+                    Arg::with_name("keepers")
+                        .long("keepers")
+                        .value_name("NAMES")
+                        .help("comma separated keepers, who take turns feeding the cages")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("roster")
+                        .long("roster")
+                        .value_name("FILE")
+                        .help("keeper roster JSON file, to assign keepers to the cages")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("map")
+                        .long("map")
+                        .value_name("COLUMNSxROWS")
+                        .help("place the cages on a map of this size, keeping predators away from prey")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("validate")
+                .about("check an inventory and whether its animals can be caged, without writing anything")
+                .args(&caging_args()),
+        )
+        .subcommand(
+            SubCommand::with_name("add")
+                .about("add an animal to an inventory")
+                .arg(input_arg())
+                .args(&record_args(true))
+                .arg(
+                    Arg::with_name("strength")
+                        .long("strength")
+                        .value_name("STRENGTH")
+                        .help("the strength of the animal, if the catalogue doesn't know it")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("carnivore")
+                        .long("carnivore")
+                        .help("the animal eats meat, if the catalogue doesn't know it"),
+                )
+                .arg(
+                    Arg::with_name("herbivore")
+                        .long("herbivore")
+                        .conflicts_with("carnivore")
+                        .help("the animal eats plants, if the catalogue doesn't know it"),
+                )
+                .arg(
+                    Arg::with_name("sex")
+                        .long("sex")
+                        .possible_values(&["male", "female"])
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("arrival")
+                        .long("arrival")
+                        .value_name("YYYY-MM-DD")
+                        .help("the day the animal arrives, for the quarantine")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("habitat")
+                        .long("habitat")
+                        .value_name("HABITAT")
+                        .help("the habitat of the animal, if it differs from its species")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("remove")
+                .about("remove an animal from an inventory")
+                .arg(input_arg())
+                .args(&record_args(false)),
+        )
+        .subcommand(
+            SubCommand::with_name("diff")
+                .about("list the animals that changed cages between two layouts")
+                .arg(
+                    Arg::with_name("OLD")
+                        .required(true)
+                        .help("the old cage layout JSON file"),
+                )
+                .arg(
+                    Arg::with_name("NEW")
+                        .required(true)
+                        .help("the new cage layout JSON file"),
+                )
+                .arg(
+                    Arg::with_name("json")
+                        .long("json")
+                        .help("print the changes as JSON"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("schema")
+                .about("print the JSON schema of an inventory"),
+        )
+        .subcommand(
+            SubCommand::with_name("completions")
+                .about("generate shell completions")
+                .arg(
+                    Arg::with_name("SHELL")
+                        .required(true)
+                        .possible_values(&Shell::variants())
+                        .help("the shell to generate the completions for"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("man")
+                .about("generate the man page"),
+        )
+        .subcommand(
+            SubCommand::with_name("config")
+                .about("inspect the configuration")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(
+                    SubCommand::with_name("show")
+                        .about("print the effective configuration of a subcommand")
+                        .args(&caging_args())
+                        .arg(
+                            Arg::with_name("for")
+                                .long("for")
+                                .value_name("COMMAND")
+                                .help("the subcommand to show the configuration of")
+                                .default_value("cage"),
+                        ),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("stats")
+                .about("summarise an inventory and its cage layout")
+                .args(&caging_args())
+                .arg(
+                    Arg::with_name("layout")
+                        .long("layout")
+                        .short("l")
+                        .value_name("FILE")
+                        .help("cage layout JSON file, instead of caging the animals first")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("growth")
+                        .long("growth")
+                        .value_name("STRENGTH")
+                        .help("how much a predator may grow before a cage is at risk")
+                        .default_value(growth_spurt),
+                )
+                .arg(
+                    Arg::with_name("json")
+                        .long("json")
+                        .help("print the statistics as JSON"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("health-report")
+                .about("list the animals due for a check by the vet, by cage")
+                .args(&caging_args())
+                .arg(
+                    Arg::with_name("within")
+                        .long("within")
+                        .value_name("DAYS")
+                        .help("also list the checks due within DAYS after today")
+                        .default_value("7"),
+                )
+                .arg(
+                    Arg::with_name("json")
+                        .long("json")
+                        .help("print the report as JSON"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("federate")
+                .about("propose transfers between zoos, INPUT lists their names and inventories")
+                .args(&caging_args())
+                .arg(
+                    Arg::with_name("goal")
+                        .long("goal")
+                        .possible_values(&["cages", "balance"])
+                        .help("use fewer cages, or even out how many species the zoos keep")
+                        .default_value("cages"),
+                )
+                .arg(
+                    Arg::with_name("manifest")
+                        .long("manifest")
+                        .value_name("FILE")
+                        .help("write the transfer manifest as CSV to FILE")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("json")
+                        .long("json")
+                        .help("print the transfers as JSON"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("simulate")
+                .about("estimate how likely predation incidents are, cage by cage")
+                .args(&caging_args())
+                .arg(
+                    Arg::with_name("trials")
+                        .long("trials")
+                        .value_name("N")
+                        .help("how many days to simulate")
+                        .default_value("10000"),
+                )
+                .arg(
+                    Arg::with_name("door-probability")
+                        .long("door-probability")
+                        .value_name("P")
+                        .help("probability of a door between neighbouring cages being open")
+                        .default_value("0.01"),
+                )
+                .arg(
+                    Arg::with_name("strength-error")
+                        .long("strength-error")
+                        .value_name("RATIO")
+                        .help("how far off the strength estimates may be, e.g. 0.1 for 10%")
+                        .default_value("0.1"),
+                )
+                .arg(
+                    Arg::with_name("seed")
+                        .long("seed")
+                        .value_name("N")
+                        .help("seed of the random number generator")
+                        .default_value("0"),
+                )
+                .arg(
+                    Arg::with_name("map")
+                        .long("map")
+                        .value_name("COLUMNSxROWS")
+                        .help("place the cages on a map of this size to find their neighbours")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("json")
+                        .long("json")
+                        .help("print the report as JSON"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("transfer")
+                .about("plan the trips that move the animals from one cage layout to another")
+                .arg(
+                    Arg::with_name("CURRENT")
+                        .required(true)
+                        .help("the current cage layout JSON file"),
+                )
+                .arg(
+                    Arg::with_name("TARGET")
+                        .required(true)
+                        .help("the target cage layout JSON file"),
+                )
+                .arg(
+                    Arg::with_name("max-strength")
+                        .long("max-strength")
+                        .value_name("STRENGTH")
+                        .help("the total strength the transport crate can carry")
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("max-animals")
+                        .long("max-animals")
+                        .value_name("N")
+                        .help("how many animals fit into the transport crate")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("json")
+                        .long("json")
+                        .help("print the schedule as JSON"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("watch")
+                .about("cage the animals again whenever the input file changes")
+                .args(&caging_args())
+                .arg(output_dir_arg())
+                .arg(
+                    Arg::with_name("interval")
+                        .long("interval")
+                        .value_name("MS")
+                        .help("how often to check the input file for changes")
+                        .default_value("1000"),
+                )
+                .arg(
+                    Arg::with_name("debounce")
+                        .long("debounce")
+                        .value_name("MS")
+                        .help("how long the input file must be unchanged before it is read")
+                        .default_value("300"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("generate")
+                .about("generate a synthetic inventory")
+                .arg(
+                    Arg::with_name("species")
+                        .long("species")
+                        .short("s")
+                        .value_name("N")
+                        .help("number of species")
+                        .default_value("10"),
+                )
+                .arg(
+                    Arg::with_name("animals")
+                        .long("animals")
+                        .short("a")
+                        .value_name("RANGE")
+                        .help("number of animals per species: N, MIN..=MAX or MIN..MAX (excluding MAX)")
+                        .default_value("1..=3"),
+                )
+                .arg(
+                    Arg::with_name("carnivores")
+                        .long("carnivores")
+                        .short("c")
+                        .value_name("RATIO")
+                        .help("share of carnivore species between 0 and 1")
+                        .default_value("0.5"),
+                )
+                .arg(
+                    Arg::with_name("strength")
+                        .long("strength")
+                        .value_name("DISTRIBUTION")
+                        .help("uniform:MIN:MAX, log:MIN:MAX or normal:MEAN:STD_DEV")
+                        .default_value("log:1:10000"),
+                )
+                .arg(
+                    Arg::with_name("variation")
+                        .long("variation")
+                        .value_name("RATIO")
+                        .help("how much animals may differ from the strength of their species")
+                        .default_value("0.2"),
+                )
+                .arg(
+                    Arg::with_name("shape")
+                        .long("shape")
+                        .possible_values(&["realistic", "equal-carnivores", "near-misses"])
+                        .help("adversarial shapes stress rule 5 and `fitting_cage`")
+                        .default_value("realistic"),
+                )
+                .arg(
+                    Arg::with_name("seed")
+                        .long("seed")
+                        .value_name("N")
+                        .help("seed of the random number generator")
+                        .default_value("0"),
+                ),
+        )
+}
 
-    // Read the contents of the JSON file passed through the arguments
-    let input_file = PathBuf::from(input);
-    let input_json = fs::read_to_string(&input_file)?;
+/// How much is printed to stderr: 0 for errors only, 1 also for warnings and
+/// 2 or more also for what is being done.
+static VERBOSITY: AtomicU64 = AtomicU64::new(1);
+/// Whether `log` colors its labels.
+static COLOR: AtomicBool = AtomicBool::new(false);
 
-    // Let serde deserialize the json into the specified type `Vec<Animals>`
-    let animals: Vec<Animal> = serde_json::from_str(&input_json)?;
+fn error(message: impl Display) {
+    log(0, "error", "1;31", message);
+}
 
-    // Run the library function to put the animals in cages
-    let cages = cage_em_all(animals, food)?;
+fn warning(message: impl Display) {
+    log(1, "warning", "1;33", message);
+}
+
+fn info(message: impl Display) {
+    log(2, "info", "1;36", message);
+}
+
+/// Prints `message` to stderr with a `label` in the ANSI `color`, if the
+/// `VERBOSITY` is at least `level`.
+fn log(level: u64, label: &str, color: &str, message: impl Display) {
+    if VERBOSITY.load(Ordering::Relaxed) < level {
+        return;
+    }
+    if COLOR.load(Ordering::Relaxed) {
+        eprintln!("\x1b[{}m{}\x1b[0m: {}", color, label, message);
+    } else {
+        eprintln!("{}: {}", label, message);
+    }
+}
+
+/// Where the subcommands print to: the `--output` file or stdout.
+fn output(matches: &ArgMatches) -> Result<Box<dyn Write>> {
+    Ok(match matches.value_of("output") {
+        Some(path) => Box::new(fs::File::create(path)?),
+        None => Box::new(io::stdout()),
+    })
+}
+
+/// The file formats of an inventory.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Format {
+    Json,
+    /// The animals are an array of tables, `[[animals]]`
+    Toml,
+}
+
+/// An inventory in TOML, which can't be a plain array.
+#[derive(Serialize, Deserialize)]
+struct TomlInventory {
+    animals: Vec<Record>,
+}
+
+impl Format {
+    /// The format of `path`: the `--format`, or else the one its extension
+    /// says, JSON by default.
+    fn of(path: &Path, format: Option<&str>) -> Self {
+        let format = format.or_else(|| path.extension()?.to_str());
+        match format {
+            Some("toml") => Self::Toml,
+            _ => Self::Json,
+        }
+    }
+
+    fn read(self, path: &Path) -> Result<Vec<Record>> {
+        let text = fs::read_to_string(path)?;
+        Ok(match self {
+            Self::Json => serde_json::from_str(&text)?,
+            Self::Toml => {
+                let inventory: TomlInventory = toml::from_str(&text)
+                    .map_err(|err| Error::InvalidValue(format!("{}: {}", path.display(), err)))?;
+                inventory.animals
+            }
+        })
+    }
+
+    fn write(self, path: &Path, records: Vec<Record>) -> Result<()> {
+        let text = match self {
+            Self::Json => serde_json::to_string_pretty(&records)? + "\n",
+            Self::Toml => toml::to_string(&TomlInventory { animals: records })
+                .map_err(|err| Error::InvalidValue(err.to_string()))?,
+        };
+        fs::write(path, text)?;
+        Ok(())
+    }
+}
+
+/// The options of a subcommand, taken from the command line, the
+/// configuration file or the defaults of the options, in this order.
+struct Settings<'a> {
+    matches: &'a ArgMatches<'a>,
+    /// The values of the configuration file for the subcommand, and where
+    /// they come from
+    config: BTreeMap<String, (String, String)>,
+}
+
+impl<'a> Settings<'a> {
+    fn new(command: &str, matches: &'a ArgMatches<'a>, config: Option<&(PathBuf, Config)>) -> Self {
+        let mut values = BTreeMap::new();
+        if let Some((path, config)) = config {
+            let path = path.display();
+            for (key, value) in config.options.values() {
+                values.insert(key, (value, path.to_string()));
+            }
+            if let Some(overrides) = config.overrides.get(command) {
+                let source = format!("{} [overrides.{}]", path, command);
+                for (key, value) in overrides.values() {
+                    values.insert(key, (value, source.clone()));
+                }
+            }
+        }
+        Self {
+            matches,
+            config: values,
+        }
+    }
+
+    /// The value of the option `name` and where it comes from.
+    fn lookup(&self, name: &str) -> Option<(&str, &str)> {
+        if self.matches.occurrences_of(name) > 0 {
+            return self
+                .matches
+                .value_of(name)
+                .map(|value| (value, "command line"));
+        }
+        // The configuration file calls `INPUT` `input`
+        let key = match name {
+            "INPUT" => "input",
+            name => name,
+        };
+        match self.config.get(key) {
+            Some((value, source)) => Some((value, source)),
+            None => self.matches.value_of(name).map(|value| (value, "default")),
+        }
+    }
+
+    fn value_of(&self, name: &str) -> Option<&str> {
+        self.lookup(name).map(|(value, _)| value)
+    }
+
+    fn is_present(&self, name: &str) -> bool {
+        self.matches.is_present(name) || self.value_of(name).is_some_and(|value| value != "false")
+    }
+
+    fn input(&self) -> Result<&Path> {
+        self.value_of("INPUT").map(Path::new).ok_or_else(|| {
+            Error::InvalidValue(format!(
+                "no INPUT given, and {} doesn't set `input` either",
+                config::FILE_NAME
+            ))
+        })
+    }
+
+    /// The path the output files of `input` are named after, in the
+    /// `--output-dir`.
+    fn output(&self, input: &Path) -> Result<PathBuf> {
+        let dir = Path::new(self.value_of("output-dir").unwrap_or(""));
+        if !dir.as_os_str().is_empty() {
+            fs::create_dir_all(dir)?;
+        }
+        Ok(dir.join(input.file_name().expect("input was a file")))
+    }
+}
+
+/// Prints every option of the configuration file that is set for the
+/// subcommand, as TOML, with where its value comes from.
+fn show_config(matches: &ArgMatches, config: Option<&(PathBuf, Config)>) -> Result<()> {
+    let matches = match matches.subcommand() {
+        ("show", Some(matches)) => matches,
+        _ => unreachable!("`show` is the only subcommand of `config`"),
+    };
+    let settings = Settings::new(matches.value_of("for").unwrap(), matches, config);
+    let mut out = output(matches)?;
+    match config {
+        Some((path, _)) => writeln!(out, "# configuration file: {}", path.display())?,
+        None => writeln!(out, "# no configuration file")?,
+    }
+
+    for key in Options::KEYS {
+        let name = match *key {
+            "input" => "INPUT",
+            key => key,
+        };
+        if let Some((value, source)) = settings.lookup(name) {
+            let value = if let Ok(value) = value.parse::<bool>() {
+                toml::Value::Boolean(value)
+            } else if let Ok(value) = value.parse::<i64>() {
+                toml::Value::Integer(value)
+            } else if let Ok(value) = value.parse::<f64>() {
+                toml::Value::Float(value)
+            } else {
+                toml::Value::String(value.to_string())
+            };
+            writeln!(out, "{} = {} # {}", key, value, source)?;
+        }
+    }
+    Ok(())
+}
+
+/// Where `cage` and `watch` write their output files.
+fn output_dir_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("output-dir")
+        .long("output-dir")
+        .value_name("DIR")
+        .help("write the output files to DIR instead of the working directory")
+        .takes_value(true)
+}
+
+/// The inventory, which may also come from the configuration file.
+fn input_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("INPUT")
+        .help("inventory JSON or TOML file, instead of `input` of the configuration")
+}
+
+/// The arguments picking an animal of an inventory.
+fn record_args<'a, 'b>(species_required: bool) -> Vec<Arg<'a, 'b>> {
+    vec![
+        Arg::with_name("name")
+            .long("name")
+            .value_name("NAME")
+            .help("the name of the animal")
+            .required(true),
+        Arg::with_name("species")
+            .long("species")
+            .value_name("SPECIES")
+            .help("the species of the animal")
+            .required(species_required),
+    ]
+}
+
+/// The arguments for reading and caging an inventory, shared by all
+/// subcommands.
+fn caging_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    vec![
+        input_arg(),
+        Arg::with_name("food")
+            .long("food")
+            .short("f")
+            .help("specify an animal that should be used as food")
+            .takes_value(true),
+        Arg::with_name("where")
+            .long("where")
+            .short("w")
+            .value_name("EXPR")
+            .help("only cage the animals matching the filter expression")
+            .takes_value(true),
+        Arg::with_name("diet-conflicts")
+            .long("diet-conflicts")
+            .value_name("POLICY")
+            .possible_values(&["error", "carnivore", "majority"])
+            .help("what to do with species whose records disagree on the diet")
+            .default_value("error"),
+        Arg::with_name("max-strength-ratio")
+            .long("max-strength-ratio")
+            .value_name("RATIO")
+            .help("reject species whose strongest animal is RATIO times stronger than the weakest")
+            .takes_value(true),
+        Arg::with_name("species-file")
+            .long("species-file")
+            .value_name("FILE")
+            .help("JSON file mapping canonical species names to their aliases")
+            .takes_value(true),
+        Arg::with_name("catalogue")
+            .long("catalogue")
+            .value_name("FILE")
+            .help("JSON file describing the species, to fill in and check the records")
+            .takes_value(true),
+        Arg::with_name("quarantine")
+            .long("quarantine")
+            .value_name("DAYS")
+            .help("how long new arrivals stay in quarantine [default: 30]")
+            .takes_value(true),
+        Arg::with_name("today")
+            .long("today")
+            .value_name("YYYY-MM-DD")
+            .help("the day to make the layout for, instead of today")
+            .takes_value(true),
+        Arg::with_name("cost")
+            .long("cost")
+            .value_name("FILE")
+            .help("JSON cost model, to make the cheapest layout instead of the smallest")
+            .takes_value(true),
+        Arg::with_name("risk")
+            .long("risk")
+            .value_name("FILE")
+            .help("JSON risk model, weighing what could go wrong in a cage")
+            .takes_value(true),
+        Arg::with_name("max-risk")
+            .long("max-risk")
+            .value_name("RISK")
+            .help("reject layouts with a higher total risk")
+            .takes_value(true),
+        Arg::with_name("placement")
+            .long("placement")
+            .value_name("CRITERIA")
+            .help(
+                "which cage a herbivore prefers: comma separated `strongest`, `weakest` \
+                 or `balanced`, later ones break ties",
+            )
+            .default_value("strongest"),
+        Arg::with_name("explain")
+            .long("explain")
+            .help("explain why the animals can't be caged and suggest how to fix it"),
+    ]
+}
+
+/// How an inventory is read, configured by the `caging_args`.
+struct InventoryOptions {
+    /// The `--format` of the inventories, if it isn't guessed
+    format: Option<String>,
+    /// The day to make the layout for
+    today: NaiveDate,
+    filter: Option<Filter>,
+    consistency: Consistency,
+    registry: Registry,
+    caging: Caging,
+    /// Leave catalogue conflicts to the caging, so that `--explain` can
+    /// explain them
+    explain: bool,
+}
+
+impl InventoryOptions {
+    /// The canonical name of the `--food` species.
+    fn food<'a>(&'a self, food: Option<&'a str>) -> Option<&'a str> {
+        food.map(|food| self.registry.resolve(food).unwrap_or(food))
+    }
+
+    /// Cages the `animals` and warns about the requirements of the species
+    /// the layout doesn't meet.
+    fn cage(&self, animals: Vec<Animal>, food: Option<&str>) -> Result<Layout> {
+        let layout = self.caging.cage(animals, self.food(food))?;
+        for note in &layout.notes {
+            warning(note);
+        }
+        Ok(layout)
+    }
+}
+
+/// Parses the options before doing any work, so that typos are reported early.
+fn inventory_options(matches: &Settings) -> Result<InventoryOptions> {
+    let max_strength_ratio = matches
+        .value_of("max-strength-ratio")
+        .map(|_| value_t_or_exit!(matches, "max-strength-ratio", f64));
+
+    let catalogue: Catalogue = match matches.value_of("catalogue") {
+        Some(catalogue) => serde_json::from_str(&fs::read_to_string(catalogue)?)?,
+        None => Catalogue::default(),
+    };
+    let mut registry: Registry = match matches.value_of("species-file") {
+        Some(species) => serde_json::from_str(&fs::read_to_string(species)?)?,
+        None => Registry::default(),
+    };
+    // Catalogued species are known species as well
+    registry.merge(catalogue.registry());
+
+    let today = match matches.value_of("today") {
+        Some(today) => parse_date(today)?,
+        None => Local::now().date_naive(),
+    };
+    let quarantine = Quarantine {
+        days: match matches.value_of("quarantine") {
+            Some(_) => value_t_or_exit!(matches, "quarantine", i64),
+            None => DEFAULT_QUARANTINE_DAYS,
+        },
+        today,
+    };
+
+    let cost: Option<CostModel> = match matches.value_of("cost") {
+        Some(cost) => Some(serde_json::from_str(&fs::read_to_string(cost)?)?),
+        None => None,
+    };
+    let risk: RiskModel = match matches.value_of("risk") {
+        Some(risk) => serde_json::from_str(&fs::read_to_string(risk)?)?,
+        None => RiskModel::default(),
+    };
+    let max_risk = matches
+        .value_of("max-risk")
+        .map(|_| value_t_or_exit!(matches, "max-risk", f64));
+
+    Ok(InventoryOptions {
+        format: matches.value_of("format").map(str::to_string),
+        today,
+        registry,
+        caging: Caging {
+            catalogue,
+            quarantine: Some(quarantine),
+            cost,
+            placement: matches.value_of("placement").unwrap().parse()?,
+            risk,
+            max_risk,
+        },
+        filter: matches.value_of("where").map(Filter::parse).transpose()?,
+        consistency: Consistency {
+            diet: matches.value_of("diet-conflicts").unwrap().parse()?,
+            max_strength_ratio,
+        },
+        explain: matches.is_present("explain"),
+    })
+}
+
+fn parse_date(date: &str) -> Result<NaiveDate> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|_| {
+        Error::InvalidValue(format!(
+            "invalid value `{}`, expected a date like `2024-05-01`",
+            date
+        ))
+    })
+}
+
+/// Reads the animals from `INPUT`, resolves conflicts between their records
+/// and applies the `--where` filter.
+fn read_animals(matches: &Settings) -> Result<(Vec<Animal>, InventoryOptions)> {
+    let options = inventory_options(matches)?;
+    let animals = read_inventory(matches.input()?, &options)?;
+    Ok((animals, options))
+}
+
+fn read_inventory(input: &Path, options: &InventoryOptions) -> Result<Vec<Animal>> {
+    // Let serde deserialize the file passed through the arguments into the
+    // specified type `Vec<Record>` and fill in the fields the records omit
+    // from the catalogue
+    let records = Format::of(input, options.format.as_deref()).read(input)?;
+    info(format!(
+        "read {} animals from {}",
+        records.len(),
+        input.display()
+    ));
+    let mut animals = options.caging.catalogue.complete(records)?;
+
+    // Replace aliases by canonical names and point out likely typos
+    options.registry.canonicalize(&mut animals);
+    for message in options.registry.warnings(&animals) {
+        warning(message);
+    }
 
+    // Records contradicting the catalogue are most likely typos
+    let conflicts = options.caging.catalogue.check(&animals);
+    if !conflicts.is_empty() && !options.explain {
+        return Err(Error::Inconsistent(conflicts));
+    }
+
+    // Resolve conflicts before filtering, a filter might look at the diet
+    for conflict in options.consistency.resolve(&mut animals)? {
+        warning(format!("resolved conflict: {}", conflict));
+    }
+
+    // Only keep the animals the user is interested in
+    Ok(match &options.filter {
+        Some(filter) => filter.apply(animals),
+        None => animals,
+    })
+}
+
+/// The solution for `animals.json` is written to `animals_solution.json`,
+/// the quarantine cages to `animals_quarantine.json` and the isolated animals
+/// to `animals_isolation.json`, next to the `output`.
+fn output_path(output: &Path, suffix: &str) -> PathBuf {
+    output
+        .with_file_name(format!(
+            "{}_{}",
+            output
+                .file_stem()
+                .expect("input was a file")
+                .to_string_lossy(),
+            suffix
+        ))
+        .with_extension("json")
+}
+
+fn write_solution(output: &Path, layout: &Layout) -> Result<()> {
     // Serialize the `cages: Vec<Cage>` to JSON
-    let output_json = serde_json::to_string_pretty(&cages)?;
+    let output_json = serde_json::to_string_pretty(&layout.cages)?;
 
     // Write the serialized JSON string to an output file
-    let output_file = PathBuf::from(&format!(
-        "{}_solution",
-        input_file
-            .file_stem()
-            .expect("input was a file")
-            .to_string_lossy()
-    ))
-    .with_extension("json");
-    fs::write(output_file, output_json)?;
+    fs::write(output_path(output, "solution"), output_json)?;
+
+    write_list(output_path(output, "quarantine"), &layout.quarantine)?;
+    write_list(output_path(output, "isolation"), &layout.isolation)
+}
+
+/// Writes the `items` to `path`, unless there are none. Doesn't leave an
+/// outdated list behind.
+fn write_list<T: Serialize>(path: PathBuf, items: &[T]) -> Result<()> {
+    if items.is_empty() {
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+    } else {
+        fs::write(path, serde_json::to_string_pretty(items)?)?;
+    }
+    Ok(())
+}
+
+/// Cages the `animals` and explains why they can't be caged, if asked to
+/// with `--explain`.
+fn cage_or_explain(
+    matches: &Settings,
+    animals: Vec<Animal>,
+    options: &InventoryOptions,
+) -> Result<Layout> {
+    // Run the library function to put the animals in cages
+    let food = matches.value_of("food");
+    match options.cage(animals.clone(), food) {
+        Err(err) if matches.is_present("explain") => {
+            if let Some(diagnosis) = diagnose(&options.caging, &animals, options.food(food)) {
+                eprint!("{}", diagnosis);
+            }
+            Err(err)
+        }
+        layout => layout,
+    }
+}
+
+fn cage(matches: &Settings) -> Result<()> {
+    let (animals, options) = read_animals(matches)?;
+    let grid: Option<Grid> = matches.value_of("map").map(str::parse).transpose()?;
+    let roster: Option<Roster> = match matches.value_of("roster") {
+        Some(roster) => Some(serde_json::from_str(&fs::read_to_string(roster)?)?),
+        None => None,
+    };
+    let mut out = output(matches.matches)?;
+
+    let layout = cage_or_explain(matches, animals, &options)?;
+    for cage in &layout.quarantine {
+        writeln!(
+            out,
+            "{} the {} stays in quarantine until {}",
+            cage.animal.name, cage.animal.species, cage.release
+        )?;
+    }
+    for animal in &layout.isolation {
+        writeln!(
+            out,
+            "{} the {} is contagious and stays in isolation",
+            animal.name, animal.species
+        )?;
+    }
+    if let Some(cost) = &layout.cost {
+        write!(out, "{}", cost)?;
+    }
+    if matches.is_present("risk") || matches.is_present("max-risk") {
+        write!(out, "{}", layout.risk)?;
+    }
+
+    let output = matches.output(matches.input()?)?;
+    write_solution(&output, &layout)?;
+    info(format!(
+        "wrote {} cages to {}",
+        layout.cages.len(),
+        output_path(&output, "solution").display()
+    ));
+    if matches.is_present("feeding") {
+        let keepers: Vec<String> = matches
+            .value_of("keepers")
+            .map(|keepers| keepers.split(',').map(|k| k.trim().to_string()).collect())
+            .unwrap_or_default();
+        write_feeding_plan(&output, &layout.cages, &options, &keepers)?;
+    }
+    if let Some(roster) = roster {
+        let staffing = roster.assign(&layout.cages);
+        write!(out, "{}", staffing)?;
+        for gap in &staffing.gaps {
+            warning(gap);
+        }
+    }
+
+    // The layout is fine without a map, so it is written in any case
+    if let Some(grid) = grid {
+        let map = map::place(&layout.cages, grid)?;
+        write!(out, "{}", map.render(&layout.cages))?;
+    }
+    Ok(())
+}
+
+fn validate(matches: &Settings) -> Result<()> {
+    let (animals, options) = read_animals(matches)?;
+    let count = animals.len();
+    let layout = cage_or_explain(matches, animals, &options)?;
+
+    writeln!(
+        output(matches.matches)?,
+        "{} is valid: {} animals fit into {} cages",
+        matches.input()?.display(),
+        count,
+        layout.cages.len()
+    )?;
+    Ok(())
+}
+
+/// Writes the feeding plan for `cages` to `animals_feeding.csv` and
+/// `animals_feeding.ics`, when reading from `animals.json`.
+fn write_feeding_plan(
+    output: &Path,
+    cages: &[Cage],
+    options: &InventoryOptions,
+    keepers: &[String],
+) -> Result<()> {
+    let plan = FeedingPlan::new(cages, &options.caging.catalogue, keepers);
+    for species in &plan.unknown {
+        warning(format!("the catalogue has no diet for {}", species));
+    }
+    for cage in &plan.shared_areas {
+        warning(format!(
+            "carnivores and herbivores share the feeding area of cage {}",
+            cage
+        ));
+    }
+
+    let path = output_path(output, "feeding");
+    fs::write(path.with_extension("csv"), plan.to_csv())?;
+    fs::write(path.with_extension("ics"), plan.to_ical(options.today))?;
+    Ok(())
+}
+
+fn health_report(matches: &Settings) -> Result<()> {
+    let (animals, options) = read_animals(matches)?;
+    let within = value_t_or_exit!(matches, "within", i64);
+
+    let layout = options.cage(animals, matches.value_of("food"))?;
+    let report = HealthReport::new(&layout, options.today, within);
+    let mut out = output(matches.matches)?;
+    if matches.is_present("json") {
+        writeln!(out, "{}", serde_json::to_string_pretty(&report)?)?;
+    } else {
+        write!(out, "{}", report)?;
+    }
+
+    Ok(())
+}
+
+/// A zoo of a federation, as listed in the `federate` input.
+#[derive(Deserialize)]
+struct SiteFile {
+    name: String,
+    /// The inventory JSON file, relative to the input
+    inventory: PathBuf,
+    #[serde(default)]
+    food: Option<String>,
+}
+
+fn federate(matches: &Settings) -> Result<()> {
+    let options = inventory_options(matches)?;
+    let input = matches.input()?;
+    let goal: Goal = matches.value_of("goal").unwrap().parse()?;
+
+    let files: Vec<SiteFile> = serde_json::from_str(&fs::read_to_string(input)?)?;
+    let directory = input.parent().unwrap_or_else(|| Path::new(""));
+    let sites = files
+        .into_iter()
+        .map(|file| {
+            let food = file.food.as_deref().or_else(|| matches.value_of("food"));
+            Ok(Site {
+                animals: read_inventory(&directory.join(&file.inventory), &options)?,
+                food: options.food(food).map(str::to_string),
+                name: file.name,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let manifest = federation::plan(&sites, &options.caging, goal)?;
+    if let Some(path) = matches.value_of("manifest") {
+        fs::write(path, manifest.to_csv())?;
+    }
+    let mut out = output(matches.matches)?;
+    if matches.is_present("json") {
+        writeln!(out, "{}", serde_json::to_string_pretty(&manifest)?)?;
+    } else {
+        write!(out, "{}", manifest)?;
+    }
+
+    Ok(())
+}
+
+fn simulate(matches: &Settings) -> Result<()> {
+    let (animals, options) = read_animals(matches)?;
+    let grid: Option<Grid> = matches.value_of("map").map(str::parse).transpose()?;
+    let scenario = Scenario {
+        trials: value_t_or_exit!(matches, "trials", usize),
+        door_probability: value_t_or_exit!(matches, "door-probability", f64),
+        strength_error: value_t_or_exit!(matches, "strength-error", f64),
+        seed: value_t_or_exit!(matches, "seed", u64),
+    };
+
+    let food = options.food(matches.value_of("food"));
+    let layout = options.cage(animals, food)?;
+    let map = grid
+        .map(|grid| map::place(&layout.cages, grid))
+        .transpose()?;
+    let report = simulation::simulate(&layout.cages, map.as_ref(), food, &scenario);
+    let mut out = output(matches.matches)?;
+    if matches.is_present("json") {
+        writeln!(out, "{}", serde_json::to_string_pretty(&report)?)?;
+    } else {
+        write!(out, "{}", report)?;
+    }
+
+    Ok(())
+}
+
+fn stats(matches: &Settings) -> Result<()> {
+    let (animals, options) = read_animals(matches)?;
+    let growth_spurt = value_t_or_exit!(matches, "growth", usize);
+
+    let cages: Vec<Cage> = match matches.value_of("layout") {
+        Some(layout) => serde_json::from_str(&fs::read_to_string(layout)?)?,
+        None => {
+            options
+                .cage(animals.clone(), matches.value_of("food"))?
+                .cages
+        }
+    };
+
+    let food = options.food(matches.value_of("food"));
+    let stats = Stats::new(&animals, &cages, food, growth_spurt);
+    let mut out = output(matches.matches)?;
+    if matches.is_present("json") {
+        writeln!(out, "{}", serde_json::to_string_pretty(&stats)?)?;
+    } else {
+        write!(out, "{}", stats)?;
+    }
+
+    Ok(())
+}
+
+/// A cheap fingerprint of a file, to notice when it was changed.
+fn fingerprint(path: &Path) -> Option<(SystemTime, u64)> {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+fn transfer(matches: &ArgMatches) -> Result<()> {
+    let transport = Transport {
+        max_strength: value_t_or_exit!(matches, "max-strength", usize),
+        max_animals: matches
+            .value_of("max-animals")
+            .map(|_| value_t_or_exit!(matches, "max-animals", usize)),
+    };
+    let current: Vec<Cage> =
+        serde_json::from_str(&fs::read_to_string(matches.value_of("CURRENT").unwrap())?)?;
+    let target: Vec<Cage> =
+        serde_json::from_str(&fs::read_to_string(matches.value_of("TARGET").unwrap())?)?;
+
+    let schedule = transfer::plan(&current, &target, transport)?;
+    let mut out = output(matches)?;
+    if matches.is_present("json") {
+        writeln!(out, "{}", serde_json::to_string_pretty(&schedule)?)?;
+    } else {
+        write!(out, "{}", schedule)?;
+    }
+    Ok(())
+}
+
+fn watch(matches: &Settings) -> Result<()> {
+    let input = matches.input()?;
+    let mut out = output(matches.matches)?;
+    let output = matches.output(input)?;
+    let options = inventory_options(matches)?;
+    let food = matches.value_of("food");
+    let interval = Duration::from_millis(value_t_or_exit!(matches, "interval", u64));
+    let debounce = Duration::from_millis(value_t_or_exit!(matches, "debounce", u64));
+
+    // Keeps the layout that was written last, if the input is broken
+    let mut layouts = Watch::default();
+    let mut last_seen = None;
+
+    // Without an input there would be nothing to report until it shows up
+    if let Err(err) = fs::metadata(input) {
+        return Err(Error::InvalidValue(format!(
+            "can't watch {}: {}",
+            input.display(),
+            err
+        )));
+    }
+
+    writeln!(out, "watching {}", input.display())?;
+    loop {
+        let mut current = fingerprint(input);
+        if current != last_seen {
+            // Editors often write a file in multiple steps. Wait until the file
+            // stopped changing before reading it.
+            loop {
+                thread::sleep(debounce);
+                let settled = fingerprint(input);
+                if settled == current {
+                    break;
+                }
+                current = settled;
+            }
+            last_seen = current;
+
+            let result = layouts.reload(|| {
+                read_inventory(input, &options)
+                    .and_then(|animals| options.cage(animals, food))
+                    .and_then(|layout| write_solution(&output, &layout).map(|_| layout.cages))
+            });
+            match result {
+                Ok(reload) => {
+                    write!(out, "{}", reload)?;
+                    out.flush()?;
+                }
+                Err(err) if layouts.last_good().is_some() => {
+                    error(format!("{}\nkeeping the last good layout", err))
+                }
+                Err(err) => error(err),
+            }
+        }
+
+        thread::sleep(interval);
+    }
+}
+
+fn generate(matches: &ArgMatches) -> Result<()> {
+    // Ranges work like in Rust: `..=` includes the end, `..` doesn't
+    let animals = matches.value_of("animals").unwrap();
+    let animals_per_species = if let Some((min, max)) = animals.split_once("..=") {
+        (min.parse().ok(), max.parse().ok())
+    } else if let Some((min, end)) = animals.split_once("..") {
+        let end = end.parse::<usize>().ok();
+        (min.parse().ok(), end.and_then(|end| end.checked_sub(1)))
+    } else {
+        (animals.parse().ok(), animals.parse().ok())
+    };
+    let animals_per_species = match animals_per_species {
+        (Some(min), Some(max)) if min <= max => (min, max),
+        _ => {
+            return Err(Error::InvalidValue(format!(
+                "invalid value `{}`, expected `N`, `MIN..=MAX` or `MIN..MAX`",
+                animals
+            )))
+        }
+    };
+
+    let generator = Generator {
+        seed: value_t_or_exit!(matches, "seed", u64),
+        species: value_t_or_exit!(matches, "species", usize),
+        animals_per_species,
+        carnivore_ratio: value_t_or_exit!(matches, "carnivores", f64).clamp(0.0, 1.0),
+        strength: matches
+            .value_of("strength")
+            .unwrap()
+            .parse::<Distribution>()?,
+        variation: value_t_or_exit!(matches, "variation", f64).max(0.0),
+        shape: matches.value_of("shape").unwrap().parse::<Shape>()?,
+    };
+
+    let json = serde_json::to_string_pretty(&generator.generate())?;
+    writeln!(output(matches)?, "{}", json)?;
+
+    Ok(())
+}
+
+// Only the inventory of `add` and `remove` may come from the configuration,
+// the record itself is always given on the command line.
+fn add(matches: &Settings) -> Result<()> {
+    let input = matches.input()?;
+    let format = Format::of(input, matches.value_of("format"));
+    let mut records = format.read(input)?;
+    let args = matches.matches;
+    let name = args.value_of("name").unwrap();
+    let species = args.value_of("species").unwrap();
+
+    if records
+        .iter()
+        .any(|record| record.name == name && same_species(&record.species, species))
+    {
+        return Err(Error::InvalidValue(format!(
+            "there already is {} the {} in {}",
+            name,
+            species,
+            input.display()
+        )));
+    }
+    let record = Record {
+        name: name.to_string(),
+        species: species.to_string(),
+        strength: args
+            .value_of("strength")
+            .map(|_| value_t_or_exit!(args, "strength", usize)),
+        carnivore: match (args.is_present("carnivore"), args.is_present("herbivore")) {
+            (true, _) => Some(true),
+            (_, true) => Some(false),
+            _ => None,
+        },
+        sex: args.value_of("sex").map(|sex| match sex {
+            "male" => Sex::Male,
+            _ => Sex::Female,
+        }),
+        breeding_status: None,
+        arrival: args.value_of("arrival").map(parse_date).transpose()?,
+        habitat: args.value_of("habitat").map(str::to_string),
+        health: None,
+    };
+    for (field, missing) in [
+        ("strength", record.strength.is_none()),
+        ("diet", record.carnivore.is_none()),
+    ] {
+        if missing {
+            warning(format!(
+                "{} the {} has no {}, the catalogue has to provide one",
+                name, species, field
+            ));
+        }
+    }
+
+    records.push(record);
+    format.write(input, records)?;
+    info(format!(
+        "added {} the {} to {}",
+        name,
+        species,
+        input.display()
+    ));
+    Ok(())
+}
+
+fn remove(matches: &Settings) -> Result<()> {
+    let input = matches.input()?;
+    let format = Format::of(input, matches.value_of("format"));
+    let mut records = format.read(input)?;
+    let args = matches.matches;
+    let name = args.value_of("name").unwrap();
+    let species = args.value_of("species");
+
+    let picked = |record: &Record| {
+        record.name == name && species.is_none_or(|species| same_species(&record.species, species))
+    };
+    let removed: Vec<&Record> = records.iter().filter(|record| picked(record)).collect();
+    match removed.as_slice() {
+        [] => {
+            return Err(Error::InvalidValue(format!(
+                "there is no animal called {} in {}",
+                name,
+                input.display()
+            )))
+        }
+        [record, ..] if species.is_none() && removed.len() > 1 => {
+            return Err(Error::InvalidValue(format!(
+                "there are {} animals called {}, pick one with `--species`, e.g. `--species {}`",
+                removed.len(),
+                name,
+                record.species
+            )))
+        }
+        _ => {}
+    }
+
+    records.retain(|record| !picked(record));
+    format.write(input, records)?;
+    info(format!("removed {} from {}", name, input.display()));
+    Ok(())
+}
+
+fn diff(matches: &ArgMatches) -> Result<()> {
+    let read = |name| -> Result<Vec<Cage>> {
+        let layout = fs::read_to_string(matches.value_of(name).unwrap())?;
+        Ok(serde_json::from_str(&layout)?)
+    };
+    let diff = LayoutDiff::new(&read("OLD")?, &read("NEW")?);
+
+    let mut out = output(matches)?;
+    if matches.is_present("json") {
+        writeln!(out, "{}", serde_json::to_string_pretty(&diff)?)?;
+    } else {
+        write!(out, "{}", diff)?;
+    }
+    Ok(())
+}
+
+fn schema(matches: &ArgMatches) -> Result<()> {
+    let schema = serde_json::to_string_pretty(&catalogue::inventory_schema())?;
+    writeln!(output(matches)?, "{}", schema)?;
+    Ok(())
+}
+
+fn completions(matches: &ArgMatches, mut app: App) -> Result<()> {
+    let shell = value_t_or_exit!(matches, "SHELL", Shell);
+    app.gen_completions_to("my-zoo", shell, &mut output(matches)?);
+    Ok(())
+}
+
+/// Writes a man page in roff, with the help of every subcommand.
+fn man<'a>(matches: &ArgMatches, app: impl Fn() -> App<'a, 'a>) -> Result<()> {
+    // clap reports `--help` as an error, with the help as its message
+    let help = |args: &[&str]| {
+        let args = ["my-zoo"].iter().chain(args).chain(&["--help"]);
+        match app().get_matches_from_safe(args) {
+            Err(err) if err.kind == ErrorKind::HelpDisplayed => err.message,
+            _ => unreachable!("`--help` shows the help"),
+        }
+    };
+    // Lines starting with a dot would be roff requests
+    let verbatim = |text: String| {
+        let lines: Vec<_> = text
+            .lines()
+            .map(|line| {
+                let line = line.replace('\\', "\\e");
+                match line.starts_with('.') {
+                    true => format!("\\&{}", line),
+                    false => line,
+                }
+            })
+            .collect();
+        format!(".nf\n{}\n.fi", lines.join("\n"))
+    };
 
+    let mut out = output(matches)?;
+    writeln!(out, ".TH MY-ZOO 1")?;
+    writeln!(out, ".SH NAME")?;
+    writeln!(out, "my-zoo \\- put the animals of a zoo into cages")?;
+    writeln!(out, ".SH DESCRIPTION")?;
+    writeln!(out, "{}", verbatim(help(&[])))?;
+    writeln!(out, ".SH SUBCOMMANDS")?;
+    let subcommands = SUBCOMMANDS.iter().map(|command| vec![*command]);
+    for args in subcommands.chain(Some(vec!["config", "show"])) {
+        writeln!(out, ".SS {}", args.join(" "))?;
+        writeln!(out, "{}", verbatim(help(&args)))?;
+    }
+    writeln!(out, ".SH FILES")?;
+    writeln!(out, ".TP\n.I {}", config::FILE_NAME)?;
+    writeln!(
+        out,
+        "The configuration, in the working directory, unless \\fB--config\\fR is given."
+    )?;
     Ok(())
 }
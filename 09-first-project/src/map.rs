@@ -0,0 +1,362 @@
+//! Placing the cages of a layout on a map of the zoo.
+//!
+//! Predators housed next to their prey stress the prey, even behind bars. The
+//! zoo is a `Grid` of enclosure slots, where slots that share a side are
+//! neighbours. `place` assigns every cage a slot, so that no predator lives
+//! next to a cage with an animal it would eat.
+
+use crate::error::Error;
+use crate::invariants::eats;
+use crate::{Cage, CageKind, Result};
+use serde::Serialize;
+use std::fmt::Write;
+use std::str::FromStr;
+
+/// How many cages `place` tries to put into a slot before it gives up.
+pub const MAX_STEPS: usize = 50_000;
+
+/// The largest number of slots a `Grid` parsed from a string may have.
+pub const MAX_SLOTS: usize = 10_000;
+
+/// `Grid` is a map of `rows` times `columns` enclosure slots.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Grid {
+    pub rows: usize,
+    pub columns: usize,
+}
+
+/// `Slot` is the position of an enclosure on the `Grid`, starting at 0.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Slot {
+    pub row: usize,
+    pub column: usize,
+}
+
+impl Grid {
+    pub fn slots(&self) -> usize {
+        self.rows.saturating_mul(self.columns)
+    }
+
+    /// `neighbours` tells if `a` and `b` share a side.
+    pub fn neighbours(a: Slot, b: Slot) -> bool {
+        a.row.abs_diff(b.row) + a.column.abs_diff(b.column) == 1
+    }
+
+    fn slot(&self, index: usize) -> Slot {
+        Slot {
+            row: index / self.columns,
+            column: index % self.columns,
+        }
+    }
+}
+
+/// Parses a grid size like `4x3`, with the number of columns first.
+impl FromStr for Grid {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || {
+            Error::InvalidValue(format!(
+                "invalid value `{}`, expected a map size like `4x3`",
+                s
+            ))
+        };
+        let (columns, rows) = s.split_once(['x', 'X']).ok_or_else(invalid)?;
+        let columns: usize = columns.trim().parse().map_err(|_| invalid())?;
+        let rows = rows.trim().parse().map_err(|_| invalid())?;
+        if columns == 0 || rows == 0 {
+            return Err(invalid());
+        }
+        match columns.checked_mul(rows) {
+            Some(slots) if slots <= MAX_SLOTS => Ok(Self { rows, columns }),
+            _ => Err(Error::InvalidValue(format!(
+                "invalid value `{}`, a map has at most {} slots",
+                s, MAX_SLOTS
+            ))),
+        }
+    }
+}
+
+/// `Map` is the slot of every cage of a layout.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct Map {
+    pub grid: Grid,
+    /// The slot of each cage, in the order of the layout
+    pub slots: Vec<Slot>,
+}
+
+/// `threatens` tells if any carnivore in `a` would eat an animal in `b`.
+///
+/// This isn't only the strongest one: a weaker carnivore of another species,
+/// e.g. the food, may eat what the strongest doesn't.
+fn threatens(a: &Cage, b: &Cage) -> bool {
+    a.animals
+        .iter()
+        .filter(|predator| predator.carnivore)
+        .any(|predator| b.animals.iter().any(|prey| eats(predator, prey)))
+}
+
+/// `place` assigns the `cages` to slots on the `grid`, so that no two
+/// neighbouring cages hold a predator and its prey.
+///
+/// The most constrained cages are placed first, and slots are tried row by
+/// row, so the result is deterministic. The search backtracks as soon as a
+/// cage has no slot left, and gives up after `MAX_STEPS`.
+///
+/// # Errors
+///
+/// This function errors with `Error::TooFewSlots` if the grid is too small,
+/// and with `Error::NoMapLayout` if predators and prey can't be kept apart, or
+/// no way to keep them apart was found within `MAX_STEPS`.
+pub fn place(cages: &[Cage], grid: Grid) -> Result<Map> {
+    if cages.len() > grid.slots() {
+        return Err(Error::TooFewSlots {
+            cages: cages.len(),
+            slots: grid.slots(),
+        });
+    }
+
+    // Which cages can't be neighbours
+    let conflicts: Vec<Vec<bool>> = cages
+        .iter()
+        .map(|a| {
+            cages
+                .iter()
+                .map(|b| threatens(a, b) || threatens(b, a))
+                .collect()
+        })
+        .collect();
+    let mut order: Vec<usize> = (0..cages.len()).collect();
+    order.sort_by_key(|&i| std::cmp::Reverse(conflicts[i].iter().filter(|&&c| c).count()));
+
+    let mut search = Search {
+        grid,
+        conflicts: &conflicts,
+        slots: vec![None; cages.len()],
+        taken: vec![false; grid.slots()],
+        steps: 0,
+    };
+    if !search.search(&order) {
+        return Err(Error::NoMapLayout { cages: cages.len() });
+    }
+
+    Ok(Map {
+        grid,
+        slots: search
+            .slots
+            .into_iter()
+            .map(|slot| slot.expect("every cage was placed"))
+            .collect(),
+    })
+}
+
+/// The state of the backtracking search of `place`.
+struct Search<'a> {
+    grid: Grid,
+    conflicts: &'a [Vec<bool>],
+    slots: Vec<Option<Slot>>,
+    taken: Vec<bool>,
+    /// How many cages were put into a slot so far
+    steps: usize,
+}
+
+impl Search<'_> {
+    /// Places the cages of `order`, one after the other, and backtracks if a
+    /// cage doesn't fit anywhere.
+    fn search(&mut self, order: &[usize]) -> bool {
+        let (&cage, rest) = match order.split_first() {
+            Some(split) => split,
+            None => return true,
+        };
+        // The cages are ordered by their conflicts. Without conflicts left,
+        // the remaining cages fit into any free slot.
+        if order
+            .iter()
+            .all(|&cage| !self.conflicts[cage].contains(&true))
+        {
+            let free: Vec<usize> = (0..self.grid.slots())
+                .filter(|&index| !self.taken[index])
+                .collect();
+            for (&cage, &index) in order.iter().zip(&free) {
+                self.slots[cage] = Some(self.grid.slot(index));
+            }
+            return true;
+        }
+
+        for index in 0..self.grid.slots() {
+            if !self.fits(cage, index) {
+                continue;
+            }
+            self.steps += 1;
+            if self.steps > MAX_STEPS {
+                return false;
+            }
+
+            self.slots[cage] = Some(self.grid.slot(index));
+            self.taken[index] = true;
+            // Don't go deeper if a remaining cage already has no slot left
+            let open = rest
+                .iter()
+                .all(|&other| (0..self.grid.slots()).any(|index| self.fits(other, index)));
+            if open && self.search(rest) {
+                return true;
+            }
+            self.slots[cage] = None;
+            self.taken[index] = false;
+        }
+        false
+    }
+
+    /// `fits` tells if the slot `index` is free and `cage` doesn't conflict
+    /// with the cages placed next to it.
+    fn fits(&self, cage: usize, index: usize) -> bool {
+        let slot = self.grid.slot(index);
+        !self.taken[index]
+            && self.slots.iter().enumerate().all(|(other, placed)| {
+                placed.is_none_or(|placed| {
+                    !self.conflicts[cage][other] || !Grid::neighbours(slot, placed)
+                })
+            })
+    }
+}
+
+impl Map {
+    /// `render` draws the map as ASCII art, with a legend of the `cages` it
+    /// was made for.
+    ///
+    /// Every enclosure shows the number of its cage and the kind: `C` for
+    /// carnivores, `M` for mixed and `H` for herbivores.
+    pub fn render(&self, cages: &[Cage]) -> String {
+        let mut cells = vec![vec![String::new(); self.grid.columns]; self.grid.rows];
+        for (i, (slot, cage)) in self.slots.iter().zip(cages).enumerate() {
+            let kind = match cage.kind() {
+                CageKind::Carnivores => 'C',
+                CageKind::Mixed => 'M',
+                CageKind::Herbivores => 'H',
+                CageKind::Empty => ' ',
+            };
+            cells[slot.row][slot.column] = format!("{} {}", i + 1, kind);
+        }
+
+        let width = cells.iter().flatten().map(String::len).max().unwrap_or(0) + 2;
+        let border = format!(
+            "+{}\n",
+            format!("{}+", "-".repeat(width)).repeat(self.grid.columns)
+        );
+        let mut map = border.clone();
+        for row in cells {
+            map.push('|');
+            for cell in row {
+                let _ = write!(map, "{:^width$}|", cell, width = width);
+            }
+            map.push('\n');
+            map.push_str(&border);
+        }
+
+        for (i, cage) in cages.iter().enumerate() {
+            let mut species: Vec<&str> = vec![];
+            for animal in &cage.animals {
+                if !species.contains(&animal.species.as_str()) {
+                    species.push(&animal.species);
+                }
+            }
+            let _ = writeln!(map, "{:>3}: {}", i + 1, species.join(", "));
+        }
+        map
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::animal::animal;
+
+    fn cages() -> Vec<Cage> {
+        vec![
+            Cage::from(vec![animal("Leo", "Lion", 20, true)]),
+            Cage::from(vec![animal("Marty", "Zebra", 30, false)]),
+            Cage::from(vec![animal("Bambi", "Deer", 5, false)]),
+        ]
+    }
+
+    #[test]
+    fn predators_away_from_prey() {
+        let cages = cages();
+        let map = place(&cages, "3x1".parse().unwrap()).unwrap();
+
+        // Only the zebra is safe next to the lion
+        assert_eq!(map.slots[1], Slot { row: 0, column: 1 });
+        assert!(place(&cages, "2x2".parse().unwrap()).is_ok());
+        assert!(matches!(
+            place(
+                &[cages[0].clone(), cages[2].clone()],
+                "2x1".parse().unwrap()
+            ),
+            Err(Error::NoMapLayout { cages: 2 })
+        ));
+        assert!(matches!(
+            place(&cages, "2x1".parse().unwrap()),
+            Err(Error::TooFewSlots { cages: 3, slots: 2 })
+        ));
+        assert!("3x".parse::<Grid>().is_err());
+        assert!("100x100".parse::<Grid>().is_ok());
+        assert!("101x100".parse::<Grid>().is_err());
+        assert!("9999999999x9999999999".parse::<Grid>().is_err());
+    }
+
+    #[test]
+    fn every_carnivore_threatens() {
+        // Leo doesn't eat the cub of his species, but the cat does
+        let cages = [
+            Cage::from(vec![
+                animal("Leo", "Lion", 20, true),
+                animal("Tom", "Cat", 5, true),
+            ]),
+            Cage::from(vec![animal("Simba", "Lion", 2, true)]),
+        ];
+        assert!(threatens(&cages[0], &cages[1]));
+        assert!(matches!(
+            place(&cages, "2x1".parse().unwrap()),
+            Err(Error::NoMapLayout { cages: 2 })
+        ));
+    }
+
+    #[test]
+    fn hopeless_search() {
+        // Every slot is taken, so some lion always lives next to a deer
+        let mut cages: Vec<Cage> = (0..4)
+            .map(|i| Cage::from(vec![animal(&format!("Leo {}", i), "Lion", 20, true)]))
+            .collect();
+        cages.extend((0..32).map(|i| Cage::from(vec![animal(&i.to_string(), "Deer", 5, false)])));
+
+        assert!(matches!(
+            place(&cages, "6x6".parse().unwrap()),
+            Err(Error::NoMapLayout { cages: 36 })
+        ));
+    }
+
+    #[test]
+    fn render_map() {
+        let cages = cages();
+        let map = Map {
+            grid: "2x2".parse().unwrap(),
+            slots: vec![
+                Slot { row: 0, column: 0 },
+                Slot { row: 1, column: 1 },
+                Slot { row: 0, column: 1 },
+            ],
+        };
+
+        assert_eq!(
+            map.render(&cages),
+            "+-----+-----+\n\
+             | 1 C | 3 H |\n\
+             +-----+-----+\n\
+             |     | 2 H |\n\
+             +-----+-----+\n  \
+             1: Lion\n  \
+             2: Zebra\n  \
+             3: Deer\n"
+        );
+    }
+}
@@ -0,0 +1,204 @@
+//! Which cage a herbivore goes into, if it fits into several.
+//!
+//! Rule 6 of the README puts it with the strongest carnivore, for
+//! intimidation. A `Placement` makes this preference configurable: it is a
+//! list of `Criterion`s, where every criterion breaks the ties of the ones
+//! before it. The remaining ties go to the cage that comes first in the
+//! layout.
+
+use crate::error::Error;
+use crate::{Animal, Cage, Result};
+use std::cmp::Ordering;
+use std::fmt::{self, Debug, Formatter};
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// `Score` rates a cage for a herbivore, higher is better.
+pub type Score = dyn Fn(&Cage, &Animal) -> i64 + Send + Sync;
+
+/// `Criterion` is a single preference of a `Placement`.
+#[derive(Clone)]
+pub enum Criterion {
+    /// Prefer the strongest predator, for intimidation
+    Strongest,
+    /// Prefer the weakest predator, to keep the herbivore as calm as possible
+    Weakest,
+    /// Prefer the cage with the fewest animals
+    Balanced,
+    /// Prefer the cage with the highest score. The herbivore is the weakest
+    /// animal of its species.
+    Custom(Arc<Score>),
+}
+
+impl Criterion {
+    /// `custom` creates a criterion from a scoring closure.
+    pub fn custom(score: impl Fn(&Cage, &Animal) -> i64 + Send + Sync + 'static) -> Self {
+        Self::Custom(Arc::new(score))
+    }
+
+    fn score(&self, cage: &Cage, herbivore: &Animal) -> i64 {
+        let predator = || {
+            cage.predator()
+                .map_or(0, |predator| predator.strength as i64)
+        };
+        match self {
+            Self::Strongest => predator(),
+            Self::Weakest => -predator(),
+            Self::Balanced => -(cage.animals.len() as i64),
+            Self::Custom(score) => score(cage, herbivore),
+        }
+    }
+}
+
+impl Debug for Criterion {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Strongest => f.write_str("Strongest"),
+            Self::Weakest => f.write_str("Weakest"),
+            Self::Balanced => f.write_str("Balanced"),
+            Self::Custom(_) => f.write_str("Custom(..)"),
+        }
+    }
+}
+
+/// Custom criteria are only equal if they share the same closure.
+impl PartialEq for Criterion {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Custom(a), Self::Custom(b)) => Arc::ptr_eq(a, b),
+            (Self::Strongest, Self::Strongest)
+            | (Self::Weakest, Self::Weakest)
+            | (Self::Balanced, Self::Balanced) => true,
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for Criterion {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim() {
+            "strongest" => Ok(Self::Strongest),
+            "weakest" => Ok(Self::Weakest),
+            "balanced" => Ok(Self::Balanced),
+            _ => Err(Error::InvalidValue(format!(
+                "invalid value `{}`, expected `strongest`, `weakest` or `balanced`",
+                s
+            ))),
+        }
+    }
+}
+
+/// `Placement` decides which of the cages a herbivore fits into it goes into.
+///
+/// The default is rule 6: the cage with the strongest predator.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Placement {
+    /// The preferences, most important first
+    pub criteria: Vec<Criterion>,
+}
+
+impl Default for Placement {
+    fn default() -> Self {
+        Self {
+            criteria: vec![Criterion::Strongest],
+        }
+    }
+}
+
+impl Placement {
+    /// `choose` picks the best of the `cages` for `herbivore`, among the ones
+    /// for which `candidate` holds, and returns its index.
+    pub fn choose(
+        &self,
+        cages: &[Cage],
+        herbivore: &Animal,
+        candidate: impl Fn(usize, &Cage) -> bool,
+    ) -> Option<usize> {
+        let mut best: Option<(usize, Vec<i64>)> = None;
+        for (i, cage) in cages.iter().enumerate().filter(|(i, c)| candidate(*i, c)) {
+            let scores: Vec<_> = self
+                .criteria
+                .iter()
+                .map(|criterion| criterion.score(cage, herbivore))
+                .collect();
+            // Only a strictly better cage replaces an earlier one
+            if best
+                .as_ref()
+                .is_none_or(|(_, best)| scores.cmp(best) == Ordering::Greater)
+            {
+                best = Some((i, scores));
+            }
+        }
+        best.map(|(i, _)| i)
+    }
+}
+
+/// Parses a comma separated list of criteria, e.g. `weakest,balanced`.
+impl FromStr for Placement {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(Self {
+            criteria: s.split(',').map(str::parse).collect::<Result<_>>()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::animal::animal;
+
+    fn cages() -> Vec<Cage> {
+        vec![
+            Cage::from(vec![
+                animal("Leo", "Lion", 20, true),
+                animal("Nala", "Lion", 18, true),
+            ]),
+            Cage::from(vec![animal("Wolfie", "Wolf", 10, true)]),
+            Cage::from(vec![animal("Foxy", "Fox", 5, true)]),
+        ]
+    }
+
+    fn choose(placement: &str, herbivore: &Animal) -> Option<String> {
+        let placement: Placement = placement.parse().unwrap();
+        let cages = cages();
+        placement
+            .choose(&cages, herbivore, |_, cage| cage.fits(herbivore))
+            .map(|i| cages[i].predator().unwrap().name.clone())
+    }
+
+    #[test]
+    fn built_in_criteria() {
+        let marty = animal("Marty", "Zebra", 30, false);
+
+        assert_eq!(choose("strongest", &marty).unwrap(), "Leo");
+        assert_eq!(choose("weakest", &marty).unwrap(), "Foxy");
+        assert_eq!(choose("balanced", &marty).unwrap(), "Wolfie");
+        assert_eq!(choose("balanced,weakest", &marty).unwrap(), "Foxy");
+        assert_eq!(
+            choose("strongest", &animal("Bambi", "Deer", 1, false)),
+            None
+        );
+        assert!("strongest,loudest".parse::<Placement>().is_err());
+    }
+
+    #[test]
+    fn custom_criterion() {
+        let marty = animal("Marty", "Zebra", 30, false);
+        let placement = Placement {
+            criteria: vec![Criterion::custom(|cage, herbivore| {
+                // A predator a third as strong as the herbivore is just right
+                let ideal = herbivore.strength as i64 / 3;
+                -(ideal - cage.predator().unwrap().strength as i64).abs()
+            })],
+        };
+
+        let cages = cages();
+        let cage = placement.choose(&cages, &marty, |_, cage| cage.fits(&marty));
+
+        assert_eq!(cages[cage.unwrap()].predator().unwrap().name, "Wolfie");
+    }
+}
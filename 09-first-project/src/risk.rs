@@ -0,0 +1,230 @@
+//! How risky a cage layout is.
+//!
+//! A layout that follows all the rules may still be one growth spurt away from
+//! an accident, e.g. if a herbivore is only one point stronger than its
+//! predator. `RiskModel::assess` scores every cage by what could go wrong in
+//! it, and adds the scores up for the layout. `Caging` can reject layouts
+//! above a maximum risk.
+//!
+//! The risk model is a JSON object of weights, where every field is optional:
+//!
+//! ```json
+//! { "gap": 10, "carnivore": 1, "conflict": 5, "injured": 3, "contagious": 10 }
+//! ```
+
+use crate::caging::Caging;
+use crate::species::same_species;
+use crate::{Animal, Cage};
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display, Formatter};
+
+/// `RiskModel` weighs what could go wrong in a cage.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct RiskModel {
+    /// Risk of a prey animal that is only one point stronger than the
+    /// predator of its cage. A prey animal `n` points stronger counts with
+    /// `gap / n`.
+    pub gap: f64,
+    /// Risk of every carnivore in a cage
+    pub carnivore: f64,
+    /// Risk of every pair of animals that don't get along, see `Social`
+    ///
+    /// [`Social`]: crate::catalogue::Social
+    pub conflict: f64,
+    /// Risk of every injured animal sharing a cage with a predator
+    pub injured: f64,
+    /// Risk of every contagious animal sharing a cage with others
+    pub contagious: f64,
+}
+
+impl Default for RiskModel {
+    fn default() -> Self {
+        Self {
+            gap: 10.0,
+            carnivore: 1.0,
+            conflict: 5.0,
+            injured: 3.0,
+            contagious: 10.0,
+        }
+    }
+}
+
+/// `CageRisk` is the risk of a single cage.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct CageRisk {
+    /// The position of the cage in the layout, starting at 1
+    pub cage: usize,
+    /// Risk of the prey being too close to the strength of the predator
+    pub gaps: f64,
+    pub carnivores: f64,
+    pub conflicts: f64,
+    /// Risk of injured and contagious animals
+    pub health: f64,
+    pub total: f64,
+}
+
+/// `RiskReport` is the risk of a layout, cage by cage.
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+pub struct RiskReport {
+    pub cages: Vec<CageRisk>,
+    pub total: f64,
+}
+
+impl RiskModel {
+    /// `cage_risk` is the risk of `cage`, at position `index` of a layout.
+    ///
+    /// Animals of the `food` species are supposed to be eaten and don't count
+    /// as prey. The `caging` knows which animals don't get along.
+    pub fn cage_risk(
+        &self,
+        caging: &Caging,
+        index: usize,
+        cage: &Cage,
+        food: Option<&str>,
+    ) -> CageRisk {
+        let is_food =
+            |animal: &Animal| food.is_some_and(|food| same_species(&animal.species, food));
+        let residents: Vec<_> = cage.animals.iter().filter(|a| !is_food(a)).collect();
+
+        // The predator is the strongest carnivore, everyone else is prey
+        let predator = cage.predator().filter(|predator| !is_food(predator));
+        let prey = |animal: &Animal| {
+            predator.is_some_and(|predator| !same_species(&animal.species, &predator.species))
+        };
+        let gaps = match predator {
+            Some(predator) => residents
+                .iter()
+                .filter(|animal| prey(animal))
+                .map(|animal| {
+                    let gap = animal.effective_strength() as i64 - predator.strength as i64;
+                    self.gap / gap.max(1) as f64
+                })
+                // An empty `sum` is -0
+                .fold(0.0, |sum, risk| sum + risk),
+            None => 0.0,
+        };
+
+        let carnivores = residents.iter().filter(|animal| animal.carnivore).count();
+        let mut conflicts = 0;
+        for (i, a) in residents.iter().enumerate() {
+            conflicts += residents[i + 1..]
+                .iter()
+                .filter(|b| caging.conflict(a, b))
+                .count();
+        }
+        let injured = residents
+            .iter()
+            .filter(|animal| prey(animal))
+            .filter(|animal| animal.is_injured())
+            .count();
+        let contagious = match residents.len() {
+            0 | 1 => 0,
+            _ => residents.iter().filter(|a| a.is_contagious()).count(),
+        };
+
+        let carnivores = self.carnivore * carnivores as f64;
+        let conflicts = self.conflict * conflicts as f64;
+        let health = self.injured * injured as f64 + self.contagious * contagious as f64;
+        CageRisk {
+            cage: index + 1,
+            gaps,
+            carnivores,
+            conflicts,
+            health,
+            total: gaps + carnivores + conflicts + health,
+        }
+    }
+
+    /// `assess` computes the risk of every cage in `cages`.
+    pub fn assess(&self, caging: &Caging, cages: &[Cage], food: Option<&str>) -> RiskReport {
+        let cages: Vec<_> = cages
+            .iter()
+            .enumerate()
+            .map(|(i, cage)| self.cage_risk(caging, i, cage, food))
+            .collect();
+        let total = cages.iter().map(|cage| cage.total).sum();
+        RiskReport { cages, total }
+    }
+}
+
+impl Display for RiskReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Risk: {:.2}", self.total)?;
+        for cage in &self.cages {
+            writeln!(
+                f,
+                "  cage {:>3}: {:>8.2} (gaps {:.2}, carnivores {:.2}, conflicts {:.2}, health {:.2})",
+                cage.cage, cage.total, cage.gaps, cage.carnivores, cage.conflicts, cage.health
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::animal::animal;
+    use crate::Health;
+
+    #[test]
+    fn cage_risk() {
+        let caging = Caging {
+            catalogue: serde_json::from_str(r#"{ "Tiger": { "social": { "solitary": true } } }"#)
+                .unwrap(),
+            ..Caging::default()
+        };
+        let model = RiskModel::default();
+        let cage = Cage::from(vec![
+            animal("Shere Khan", "Tiger", 20, true),
+            animal("Rajah", "Tiger", 18, true),
+            animal("Marty", "Zebra", 21, false),
+            Animal {
+                health: Some(Health {
+                    injured: true,
+                    ..Health::default()
+                }),
+                ..animal("Dumbo", "Elephant", 80, false)
+            },
+            animal("Bambi", "Deer", 5, false),
+        ]);
+
+        let risk = model.cage_risk(&caging, 0, &cage, Some("Deer"));
+
+        // Marty is one point stronger, Dumbo counts as 40, Bambi is food
+        assert_eq!(risk.gaps, 10.0 + 10.0 / 20.0);
+        assert_eq!(risk.carnivores, 2.0);
+        assert_eq!(risk.conflicts, 5.0);
+        assert_eq!(risk.health, 3.0);
+        assert_eq!(risk.total, 20.5);
+    }
+
+    #[test]
+    fn max_risk() {
+        let animals = vec![
+            animal("Leo", "Lion", 20, true),
+            animal("Marty", "Zebra", 21, false),
+        ];
+
+        let layout = Caging::default().cage(animals.clone(), None).unwrap();
+        assert_eq!(layout.cages.len(), 1);
+        assert_eq!(layout.risk.total, 11.0);
+
+        // The optimiser keeps the zebra away from the lion instead
+        let caging = Caging {
+            cost: Some(Default::default()),
+            max_risk: Some(5.0),
+            ..Caging::default()
+        };
+        let layout = caging.cage(animals.clone(), None).unwrap();
+        assert_eq!(layout.cages.len(), 2);
+        assert_eq!(layout.risk.total, 1.0);
+
+        let caging = Caging {
+            max_risk: Some(5.0),
+            ..Caging::default()
+        };
+        assert!(caging.cage(animals, None).is_err());
+    }
+}
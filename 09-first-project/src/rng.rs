@@ -0,0 +1,54 @@
+/// `Rng` is a small, seedable pseudo random number generator (SplitMix64).
+///
+/// It is not suitable for cryptography, but fast and, most importantly for
+/// generating test data and simulations, reproducible: the same seed always
+/// produces the same numbers, on every platform and with every version of
+/// this crate.
+#[derive(Clone, Debug)]
+pub(crate) struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub(crate) fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A uniformly distributed number in `0.0..1.0`.
+    pub(crate) fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// A uniformly distributed number in `min..=max`.
+    pub(crate) fn range(&mut self, min: usize, max: usize) -> usize {
+        assert!(min <= max);
+        let span = ((max - min) as u64).wrapping_add(1);
+        // `span` is 0, if the range covers all of `u64`
+        min + match span {
+            0 => self.next_u64(),
+            span => self.next_u64() % span,
+        } as usize
+    }
+
+    /// A normally distributed number (Box-Muller transform).
+    pub(crate) fn normal(&mut self, mean: f64, std_dev: f64) -> f64 {
+        let u1 = 1.0 - self.next_f64();
+        let u2 = self.next_f64();
+        mean + std_dev * (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+    }
+
+    /// Shuffles `items` in place (Fisher-Yates).
+    pub(crate) fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.range(0, i));
+        }
+    }
+}
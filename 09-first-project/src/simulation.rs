@@ -0,0 +1,242 @@
+//! Monte Carlo simulation of predation incidents.
+//!
+//! A layout is only as safe as the assumptions behind it: strengths are
+//! estimates, and sooner or later a keeper leaves a door open. `simulate`
+//! plays through many days, each with some strength estimates off by up to
+//! `Scenario::strength_error` and some doors between neighbouring cages open,
+//! and counts the days on which an animal of a cage was involved in a
+//! predation incident.
+//!
+//! The simulation is reproducible: the same `Scenario` always produces the
+//! same report.
+
+use crate::invariants::eats;
+use crate::map::{Grid, Map};
+use crate::rng::Rng;
+use crate::species::same_species;
+use crate::{Animal, Cage};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::fmt::{self, Display, Formatter};
+
+/// `Scenario` configures what can go wrong on a simulated day.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct Scenario {
+    /// How many days are simulated
+    pub trials: usize,
+    /// The probability of the door between two neighbouring cages being
+    /// open on a day
+    pub door_probability: f64,
+    /// How far off a strength estimate may be, e.g. `0.1` for ±10%
+    pub strength_error: f64,
+    pub seed: u64,
+}
+
+impl Default for Scenario {
+    fn default() -> Self {
+        Self {
+            trials: 10_000,
+            door_probability: 0.01,
+            strength_error: 0.1,
+            seed: 0,
+        }
+    }
+}
+
+/// `CageIncidents` counts the simulated days on which animals of a cage were
+/// involved in an incident, as predator or as prey.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct CageIncidents {
+    /// The position of the cage in the layout, starting at 1
+    pub cage: usize,
+    /// Days with an incident of any kind
+    pub incidents: usize,
+    /// Days with an incident inside the cage, because strengths were off
+    pub within: usize,
+    /// Days with an incident with an animal of a neighbouring cage, through
+    /// an open door
+    pub doors: usize,
+    /// The share of days with an incident
+    pub probability: f64,
+}
+
+/// `SimulationReport` is the result of `simulate`.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct SimulationReport {
+    pub trials: usize,
+    /// Every cage, the most dangerous first
+    pub cages: Vec<CageIncidents>,
+}
+
+/// `simulate` runs the `scenario` on the `cages`.
+///
+/// With a `map`, cages are neighbours if their slots share a side. Without
+/// one, every cage is a neighbour of the ones before and after it in the
+/// layout. An open door only lets the animals of the two cages it connects
+/// meet. Animals of the `food` species are supposed to be eaten, so eating
+/// them isn't an incident.
+pub fn simulate(
+    cages: &[Cage],
+    map: Option<&Map>,
+    food: Option<&str>,
+    scenario: &Scenario,
+) -> SimulationReport {
+    let neighbours: Vec<(usize, usize)> = (0..cages.len())
+        .flat_map(|a| (a + 1..cages.len()).map(move |b| (a, b)))
+        .filter(|&(a, b)| match map {
+            Some(map) => Grid::neighbours(map.slots[a], map.slots[b]),
+            None => b == a + 1,
+        })
+        .collect();
+    let is_food = |animal: &Animal| food.is_some_and(|food| same_species(&animal.species, food));
+    // Does an animal of `predators` eat one of `prey`?
+    let incident = |predators: &[Animal], prey: &[Animal]| {
+        predators
+            .iter()
+            .filter(|predator| predator.carnivore && !is_food(predator))
+            .any(|predator| {
+                prey.iter()
+                    .filter(|animal| !is_food(animal))
+                    .filter(|animal| !same_species(&animal.species, &predator.species))
+                    .min_by_key(|animal| animal.effective_strength())
+                    .is_some_and(|weakest| eats(predator, weakest))
+            })
+    };
+
+    let mut rng = Rng::new(scenario.seed);
+    let mut counts = vec![(0, 0, 0); cages.len()];
+    for _ in 0..scenario.trials {
+        // The strengths the animals really have today
+        let animals: Vec<Vec<Animal>> = cages
+            .iter()
+            .map(|cage| {
+                let vary = |animal: &Animal| Animal {
+                    strength: vary(&mut rng, animal.strength, scenario.strength_error),
+                    ..animal.clone()
+                };
+                cage.animals.iter().map(vary).collect()
+            })
+            .collect();
+
+        let mut within = vec![false; cages.len()];
+        let mut doors = vec![false; cages.len()];
+        for (i, animals) in animals.iter().enumerate() {
+            within[i] = incident(animals, animals);
+        }
+        for &(a, b) in &neighbours {
+            let open = rng.next_f64() < scenario.door_probability;
+            if open && (incident(&animals[a], &animals[b]) || incident(&animals[b], &animals[a])) {
+                doors[a] = true;
+                doors[b] = true;
+            }
+        }
+
+        for (i, (incidents, within_cage, through_doors)) in counts.iter_mut().enumerate() {
+            *incidents += (within[i] || doors[i]) as usize;
+            *within_cage += within[i] as usize;
+            *through_doors += doors[i] as usize;
+        }
+    }
+
+    let mut cages: Vec<_> = counts
+        .into_iter()
+        .enumerate()
+        .map(|(i, (incidents, within, doors))| CageIncidents {
+            cage: i + 1,
+            incidents,
+            within,
+            doors,
+            probability: incidents as f64 / scenario.trials.max(1) as f64,
+        })
+        .collect();
+    // The sort is stable, equally dangerous cages stay in layout order
+    cages.sort_by_key(|cage| Reverse(cage.incidents));
+    SimulationReport {
+        trials: scenario.trials,
+        cages,
+    }
+}
+
+/// A strength that is off by up to `error` (a ratio) from `strength`.
+fn vary(rng: &mut Rng, strength: usize, error: f64) -> usize {
+    let factor = 1.0 + error * (2.0 * rng.next_f64() - 1.0);
+    (strength as f64 * factor).round().max(0.0) as usize
+}
+
+impl Display for SimulationReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Incidents in {} simulated days:", self.trials)?;
+        for (rank, cage) in self.cages.iter().enumerate() {
+            writeln!(
+                f,
+                "{:>3}. cage {:>3}: {:>6.2}% ({} inside the cage, {} through open doors)",
+                rank + 1,
+                cage.cage,
+                cage.probability * 100.0,
+                cage.within,
+                cage.doors
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::animal::animal;
+
+    fn cages() -> Vec<Cage> {
+        vec![
+            Cage::from(vec![
+                animal("Leo", "Lion", 100, true),
+                animal("Marty", "Zebra", 101, false),
+            ]),
+            Cage::from(vec![animal("Bambi", "Deer", 10, false)]),
+            Cage::from(vec![animal("Dumbo", "Elephant", 500, false)]),
+        ]
+    }
+
+    #[test]
+    fn open_doors() {
+        let scenario = Scenario {
+            trials: 100,
+            door_probability: 1.0,
+            strength_error: 0.0,
+            seed: 1,
+        };
+
+        let report = simulate(&cages(), None, None, &scenario);
+
+        let incidents: Vec<_> = report
+            .cages
+            .iter()
+            .map(|cage| (cage.cage, cage.within, cage.doors))
+            .collect();
+        // Leo gets to Bambi every day, Dumbo is safe
+        assert_eq!(incidents, [(1, 0, 100), (2, 0, 100), (3, 0, 0)]);
+
+        // Fed, Leo doesn't count
+        let report = simulate(&cages(), None, Some("Deer"), &scenario);
+        assert_eq!(report.cages[0].incidents, 0);
+    }
+
+    #[test]
+    fn wrong_strengths() {
+        let scenario = Scenario {
+            trials: 1000,
+            door_probability: 0.0,
+            strength_error: 0.1,
+            seed: 7,
+        };
+
+        let report = simulate(&cages(), None, None, &scenario);
+
+        // Marty is about as strong as Leo, so it's a toss-up
+        assert_eq!(report.cages[0].cage, 1);
+        assert!((0.3..0.7).contains(&report.cages[0].probability));
+        assert_eq!(report.cages[1].incidents, 0);
+        assert_eq!(simulate(&cages(), None, None, &scenario), report);
+    }
+}
@@ -0,0 +1,275 @@
+//! Matching of species names.
+//!
+//! Species names are typed by humans, so `"Cat"`, `"cat"` and `" CAT "` all
+//! mean the same species. On top of that, a `Registry` knows the canonical
+//! names of species and their aliases and can point out likely typos.
+
+use crate::Animal;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Display, Formatter};
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+/// `normalize` turns a species name into a key for comparisons.
+///
+/// The name is decomposed (NFKD) and stripped of combining marks, lowercased
+/// and whitespace is collapsed, so that `"Gorílla  "` and `"gorilla"` match.
+pub fn normalize(name: &str) -> String {
+    let name: String = name
+        .nfkd()
+        .filter(|c| !is_combining_mark(*c))
+        .flat_map(char::to_lowercase)
+        .collect();
+    name.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// `same_species` compares two species names after normalizing them.
+pub fn same_species(a: &str, b: &str) -> bool {
+    a == b || normalize(a) == normalize(b)
+}
+
+/// `distance` is the edit distance between two normalized names, where
+/// swapping two neighbouring characters counts as one edit.
+pub fn distance(a: &str, b: &str) -> usize {
+    let a: Vec<_> = normalize(a).chars().collect();
+    let b: Vec<_> = normalize(b).chars().collect();
+
+    // Optimal string alignment distance, keeping the last two rows
+    let mut before: Vec<usize> = vec![0; b.len() + 1];
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        let mut current = vec![i; b.len() + 1];
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            current[j] = (previous[j] + 1)
+                .min(current[j - 1] + 1)
+                .min(previous[j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                current[j] = current[j].min(before[j - 2] + 1);
+            }
+        }
+        before = std::mem::replace(&mut previous, current);
+    }
+    previous[b.len()]
+}
+
+/// `suggest` returns the candidate that `name` most likely is a typo of.
+///
+/// Longer names may contain more typos: one edit is allowed for every three
+/// characters, but at least one.
+pub fn suggest<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    let max_distance = (normalize(name).chars().count() / 3).max(1);
+    candidates
+        .into_iter()
+        .map(|candidate| (distance(name, candidate), candidate))
+        .filter(|&(distance, _)| distance > 0 && distance <= max_distance)
+        .min_by_key(|&(distance, _)| distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// Two different species look alike, if one is a typo of the other. This is
+/// stricter than `suggest`, since short names like `Cat` and `Bat` are often
+/// both correct.
+fn look_alike(a: &str, b: &str) -> bool {
+    let shorter = normalize(a)
+        .chars()
+        .count()
+        .min(normalize(b).chars().count());
+    let distance = distance(a, b);
+    distance > 0 && distance <= shorter / 5
+}
+
+/// `SpeciesWarning` points out a species name that is likely wrong.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub enum SpeciesWarning {
+    /// The species isn't in the `Registry`
+    Unknown {
+        species: String,
+        suggestion: Option<String>,
+    },
+    /// Two species of the inventory look alike
+    NearDuplicate { species: String, similar: String },
+}
+
+/// `Registry` knows the canonical names of species and their aliases.
+///
+/// It deserializes from a JSON object, mapping canonical names to aliases:
+///
+/// ```json
+/// { "Gorilla": ["Gorrilla"], "T-Rex": ["Tyrannosaurus", "Tyrannosaurus Rex"] }
+/// ```
+#[derive(Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(from = "BTreeMap<String, Vec<String>>")]
+pub struct Registry {
+    /// Normalized canonical names and aliases to canonical names
+    names: BTreeMap<String, String>,
+}
+
+impl From<BTreeMap<String, Vec<String>>> for Registry {
+    fn from(species: BTreeMap<String, Vec<String>>) -> Self {
+        let mut registry = Self::default();
+        for (canonical, aliases) in species {
+            registry.add(&canonical, &aliases);
+        }
+        registry
+    }
+}
+
+impl Registry {
+    /// `add` registers a species with its canonical name and aliases.
+    pub fn add(&mut self, canonical: &str, aliases: &[String]) {
+        self.names
+            .insert(normalize(canonical), canonical.to_string());
+        for alias in aliases {
+            self.names.insert(normalize(alias), canonical.to_string());
+        }
+    }
+
+    /// `merge` adds the species and aliases of `other` to `self`.
+    pub fn merge(&mut self, other: &Registry) {
+        self.names
+            .extend(other.names.iter().map(|(k, v)| (k.clone(), v.clone())));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// `resolve` returns the canonical name of the species `name`.
+    pub fn resolve(&self, name: &str) -> Option<&str> {
+        self.names.get(&normalize(name)).map(String::as_str)
+    }
+
+    /// The canonical names of all species.
+    pub fn species(&self) -> BTreeSet<&str> {
+        self.names.values().map(String::as_str).collect()
+    }
+
+    /// `canonicalize` replaces the species of `animals` by their canonical
+    /// name and returns how many animals were renamed.
+    pub fn canonicalize(&self, animals: &mut [Animal]) -> usize {
+        let mut renamed = 0;
+        for animal in animals {
+            if let Some(canonical) = self.resolve(&animal.species) {
+                if animal.species != canonical {
+                    animal.species = canonical.to_string();
+                    renamed += 1;
+                }
+            }
+        }
+        renamed
+    }
+
+    /// `warnings` lists the species of `animals` that are unknown to the
+    /// registry, if it isn't empty, and the species that look alike.
+    pub fn warnings(&self, animals: &[Animal]) -> Vec<SpeciesWarning> {
+        // Spellings that only differ in case or accents are the same species
+        let mut species: BTreeMap<String, &str> = BTreeMap::new();
+        for animal in animals {
+            species
+                .entry(normalize(&animal.species))
+                .or_insert(&animal.species);
+        }
+
+        let mut warnings = vec![];
+        if !self.is_empty() {
+            let known = self.species();
+            for name in species.values().filter(|name| self.resolve(name).is_none()) {
+                warnings.push(SpeciesWarning::Unknown {
+                    species: name.to_string(),
+                    suggestion: suggest(name, known.iter().copied()).map(str::to_string),
+                });
+            }
+        }
+
+        let names: Vec<_> = species.values().collect();
+        for (i, a) in names.iter().enumerate() {
+            for b in &names[i + 1..] {
+                if look_alike(a, b) {
+                    warnings.push(SpeciesWarning::NearDuplicate {
+                        species: a.to_string(),
+                        similar: b.to_string(),
+                    });
+                }
+            }
+        }
+        warnings
+    }
+}
+
+impl Display for SpeciesWarning {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unknown {
+                species,
+                suggestion: Some(suggestion),
+            } => write!(
+                f,
+                "unknown species `{}`, did you mean `{}`?",
+                species, suggestion
+            ),
+            Self::Unknown { species, .. } => write!(f, "unknown species `{}`", species),
+            Self::NearDuplicate { species, similar } => write!(
+                f,
+                "species `{}` and `{}` look alike, is one of them a typo?",
+                species, similar
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::animal::animal;
+
+    #[test]
+    fn normalized_names() {
+        assert!(same_species("Cat", " cat"));
+        assert!(same_species("Gorílla", "GORILLA"));
+        assert!(same_species("T-Rex", "t-rex"));
+        assert!(!same_species("T-Rex", "T Rex"));
+    }
+
+    #[test]
+    fn edit_distance() {
+        assert_eq!(distance("Gorrilla", "Gorilla"), 1);
+        assert_eq!(distance("cta", "Cat"), 1);
+        assert_eq!(distance("Elephant", "Elephant"), 0);
+        assert_eq!(distance("", "Dog"), 3);
+        assert_eq!(suggest("cta", vec!["Dog", "Cat"]), Some("Cat"));
+        assert_eq!(suggest("Lion", vec!["Dog", "Cat"]), None);
+    }
+
+    #[test]
+    fn registry() {
+        let registry: Registry =
+            serde_json::from_str(r#"{ "Gorilla": ["Gorrilla"], "Dog": [] }"#).unwrap();
+        let mut animals = vec![
+            animal("A", "gorrilla", 1, false),
+            animal("A", "dog", 1, false),
+            animal("A", "Dgo", 1, false),
+        ];
+
+        assert_eq!(registry.canonicalize(&mut animals), 2);
+        assert_eq!(animals[0].species, "Gorilla");
+        assert_eq!(
+            registry.warnings(&animals),
+            [SpeciesWarning::Unknown {
+                species: String::from("Dgo"),
+                suggestion: Some(String::from("Dog"))
+            }]
+        );
+        assert_eq!(
+            Registry::default().warnings(&[
+                animal("A", "Gorilla", 1, false),
+                animal("A", "Gorrilla", 1, false)
+            ]),
+            [SpeciesWarning::NearDuplicate {
+                species: String::from("Gorilla"),
+                similar: String::from("Gorrilla")
+            }]
+        );
+    }
+}
@@ -0,0 +1,316 @@
+//! Assigning keepers to the cages of a layout.
+//!
+//! Every cage needs a keeper, some need keepers with a qualification, e.g. for
+//! big cats or reptiles, and cages with very strong animals need two keepers.
+//! The `Roster` lists the keepers and these rules. `Roster::assign` assigns
+//! the keepers to the cages, without exceeding their shifts, and reports the
+//! cages it couldn't staff.
+//!
+//! The roster is a JSON object:
+//!
+//! ```json
+//! {
+//!     "keepers": [
+//!         { "name": "Alice", "qualifications": ["big-cat"], "shift": 8 },
+//!         { "name": "Bob", "shift": 6 }
+//!     ],
+//!     "rules": {
+//!         "qualifications": { "Lion": "big-cat", "Python": "reptile" },
+//!         "two_keepers_above": 1000,
+//!         "hours_per_cage": 1.5,
+//!         "hours_per_animal": 0.25
+//!     }
+//! }
+//! ```
+
+use crate::species::same_species;
+use crate::Cage;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Display, Formatter};
+
+/// `Keeper` is a member of the staff.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Keeper {
+    pub name: String,
+    #[serde(default)]
+    pub qualifications: BTreeSet<String>,
+    /// How many hours the keeper works per day
+    pub shift: f64,
+}
+
+/// `Rules` derive what a cage needs from its animals.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct Rules {
+    /// The qualification needed for the animals of a species
+    pub qualifications: BTreeMap<String, String>,
+    /// Cages whose strongest animal is stronger than this need two keepers
+    pub two_keepers_above: Option<usize>,
+    /// How many hours a keeper spends on a cage per day
+    pub hours_per_cage: f64,
+    /// How many additional hours a keeper spends per animal
+    pub hours_per_animal: f64,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Self {
+            qualifications: BTreeMap::new(),
+            two_keepers_above: None,
+            hours_per_cage: 1.0,
+            hours_per_animal: 0.0,
+        }
+    }
+}
+
+/// `Roster` lists the keepers and the rules for staffing the cages.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Roster {
+    pub keepers: Vec<Keeper>,
+    #[serde(default)]
+    pub rules: Rules,
+}
+
+/// `Requirement` is what a cage needs to be looked after.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct Requirement {
+    /// The position of the cage in the layout, starting at 1
+    pub cage: usize,
+    /// Every keeper of the cage needs all of these
+    pub qualifications: BTreeSet<String>,
+    pub keepers: usize,
+    /// How many hours each of the keepers spends on the cage
+    pub hours: f64,
+}
+
+/// `Gap` is a cage that didn't get all the keepers it needs.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct Gap {
+    pub requirement: Requirement,
+    /// The keepers it got
+    pub keepers: Vec<String>,
+    /// Whether there are enough qualified keepers at all, but their shifts
+    /// are full
+    pub out_of_hours: bool,
+}
+
+/// `Staffing` is the result of `Roster::assign`.
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+pub struct Staffing {
+    /// The keepers of every cage, in the order of the layout
+    pub cages: Vec<Vec<String>>,
+    pub gaps: Vec<Gap>,
+    /// How many hours of their shift every keeper works
+    pub hours: BTreeMap<String, f64>,
+}
+
+impl Roster {
+    /// `requirement` derives what the `i`th `cage` of a layout needs.
+    pub fn requirement(&self, i: usize, cage: &Cage) -> Requirement {
+        let rules = &self.rules;
+        let qualifications = cage
+            .animals
+            .iter()
+            .flat_map(|animal| {
+                rules
+                    .qualifications
+                    .iter()
+                    .filter(move |(species, _)| same_species(species, &animal.species))
+                    .map(|(_, qualification)| qualification.clone())
+            })
+            .collect();
+        let strong = rules.two_keepers_above.is_some_and(|threshold| {
+            cage.strongest()
+                .is_some_and(|strongest| strongest.strength > threshold)
+        });
+
+        Requirement {
+            cage: i + 1,
+            qualifications,
+            keepers: if strong { 2 } else { 1 },
+            hours: rules.hours_per_cage + rules.hours_per_animal * cage.animals.len() as f64,
+        }
+    }
+
+    /// `assign` assigns keepers to the `cages`.
+    ///
+    /// The most demanding cages are staffed first. Of the qualified keepers
+    /// with enough time left, the one with the most time left is chosen, so
+    /// that the work is spread evenly.
+    pub fn assign(&self, cages: &[Cage]) -> Staffing {
+        let mut requirements: Vec<_> = cages
+            .iter()
+            .enumerate()
+            .map(|(i, cage)| self.requirement(i, cage))
+            .collect();
+        requirements.sort_by_key(|requirement| {
+            (
+                Reverse(requirement.qualifications.len()),
+                Reverse(requirement.keepers),
+                requirement.cage,
+            )
+        });
+
+        let mut left: Vec<f64> = self.keepers.iter().map(|keeper| keeper.shift).collect();
+        let mut staffing = Staffing {
+            cages: vec![vec![]; cages.len()],
+            ..Staffing::default()
+        };
+        for requirement in requirements {
+            let qualified: Vec<usize> = (0..self.keepers.len())
+                .filter(|&k| {
+                    requirement
+                        .qualifications
+                        .is_subset(&self.keepers[k].qualifications)
+                })
+                .collect();
+
+            let mut assigned = vec![];
+            for _ in 0..requirement.keepers {
+                let keeper = qualified
+                    .iter()
+                    .copied()
+                    .filter(|k| !assigned.contains(k) && left[*k] >= requirement.hours)
+                    .max_by(|&a, &b| left[a].total_cmp(&left[b]).then(b.cmp(&a)));
+                match keeper {
+                    Some(k) => {
+                        left[k] -= requirement.hours;
+                        assigned.push(k);
+                    }
+                    None => break,
+                }
+            }
+
+            let names: Vec<_> = assigned
+                .iter()
+                .map(|&k| self.keepers[k].name.clone())
+                .collect();
+            if assigned.len() < requirement.keepers {
+                staffing.gaps.push(Gap {
+                    out_of_hours: qualified.len() >= requirement.keepers,
+                    keepers: names.clone(),
+                    requirement: requirement.clone(),
+                });
+            }
+            staffing.cages[requirement.cage - 1] = names;
+        }
+        staffing.gaps.sort_by_key(|gap| gap.requirement.cage);
+
+        for (keeper, left) in self.keepers.iter().zip(left) {
+            staffing
+                .hours
+                .insert(keeper.name.clone(), keeper.shift - left);
+        }
+        staffing
+    }
+}
+
+impl Display for Gap {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let requirement = &self.requirement;
+        write!(
+            f,
+            "cage {} needs {} keeper(s)",
+            requirement.cage, requirement.keepers
+        )?;
+        if !requirement.qualifications.is_empty() {
+            let qualifications: Vec<_> = requirement.qualifications.iter().cloned().collect();
+            write!(f, " qualified for {}", qualifications.join(", "))?;
+        }
+        write!(f, ", but only got {}", self.keepers.len())?;
+        if self.out_of_hours {
+            write!(f, " (the qualified keepers' shifts are full)")?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for Staffing {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Keepers:")?;
+        for (i, keepers) in self.cages.iter().enumerate() {
+            let keepers = match keepers.is_empty() {
+                true => String::from("-"),
+                false => keepers.join(", "),
+            };
+            writeln!(f, "  cage {:>3}: {}", i + 1, keepers)?;
+        }
+        writeln!(f, "Hours:")?;
+        for (keeper, hours) in &self.hours {
+            writeln!(f, "  {}: {:.2}", keeper, hours)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::animal::animal;
+
+    fn roster() -> Roster {
+        serde_json::from_str(
+            r#"{
+                "keepers": [
+                    { "name": "Alice", "qualifications": ["big-cat"], "shift": 4 },
+                    { "name": "Bob", "qualifications": ["big-cat"], "shift": 2 },
+                    { "name": "Carol", "shift": 8 }
+                ],
+                "rules": {
+                    "qualifications": { "lion": "big-cat" },
+                    "two_keepers_above": 100,
+                    "hours_per_cage": 1,
+                    "hours_per_animal": 0.5
+                }
+            }"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn requirements() {
+        let roster = roster();
+        let cage = Cage::from(vec![
+            animal("Leo", "Lion", 50, true),
+            animal("Dumbo", "Elephant", 200, false),
+        ]);
+
+        let requirement = roster.requirement(0, &cage);
+
+        assert_eq!(requirement.cage, 1);
+        assert_eq!(requirement.keepers, 2);
+        assert!(requirement.qualifications.contains("big-cat"));
+        assert_eq!(requirement.hours, 2.0);
+    }
+
+    #[test]
+    fn assignment() {
+        let roster = roster();
+        let cages = [
+            Cage::from(vec![animal("Marty", "Zebra", 20, false)]),
+            Cage::from(vec![
+                animal("Leo", "Lion", 50, true),
+                animal("Dumbo", "Elephant", 200, false),
+            ]),
+            Cage::from(vec![animal("Nala", "Lion", 40, true)]),
+        ];
+
+        let staffing = roster.assign(&cages);
+
+        assert_eq!(staffing.cages[0], ["Carol"]);
+        assert_eq!(staffing.cages[1], ["Alice", "Bob"]);
+        assert_eq!(staffing.cages[2], ["Alice"]);
+        assert!(staffing.gaps.is_empty());
+        assert_eq!(staffing.hours["Alice"], 3.5);
+
+        // Nobody is left for another lion
+        let mut cages = cages.to_vec();
+        cages.push(Cage::from(vec![animal("Simba", "Lion", 30, true)]));
+        let staffing = roster.assign(&cages);
+        assert_eq!(staffing.gaps.len(), 1);
+        assert_eq!(staffing.gaps[0].requirement.cage, 4);
+        assert!(staffing.gaps[0].out_of_hours);
+    }
+}
@@ -0,0 +1,371 @@
+//! Statistics about an inventory of animals and the cages they live in.
+
+use crate::species::normalize;
+use crate::{Animal, Cage, CageKind};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::{self, Display, Formatter};
+
+/// By how much the strength of a predator may grow by default, before a cage
+/// is considered to be at risk.
+pub const DEFAULT_GROWTH_SPURT: usize = 5;
+
+/// `Stats` summarises an inventory and a cage layout for it.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct Stats {
+    pub inventory: InventoryStats,
+    pub layout: LayoutStats,
+}
+
+impl Stats {
+    /// `new` computes the statistics for `animals` and the `cages` they were
+    /// put in.
+    ///
+    /// A cage is at risk, if its predator only has to grow by `growth_spurt`
+    /// to be able to eat one of its cagemates. The `food` species doesn't
+    /// count, it is supposed to be eaten.
+    pub fn new(
+        animals: &[Animal],
+        cages: &[Cage],
+        food: Option<&str>,
+        growth_spurt: usize,
+    ) -> Self {
+        Self {
+            inventory: InventoryStats::new(animals),
+            layout: LayoutStats::new(cages, food, growth_spurt),
+        }
+    }
+}
+
+/// `InventoryStats` summarises an inventory of animals.
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+pub struct InventoryStats {
+    pub animals: usize,
+    pub carnivores: usize,
+    pub herbivores: usize,
+    /// Number of animals per species. Different spellings of a species count
+    /// as one, under the first spelling.
+    pub species: BTreeMap<String, usize>,
+    /// Histogram of the strengths, with one bucket per order of magnitude
+    pub strength: Vec<StrengthBucket>,
+}
+
+/// `StrengthBucket` counts the animals with a strength in `min..=max`.
+#[derive(Serialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct StrengthBucket {
+    pub min: usize,
+    pub max: usize,
+    pub carnivores: usize,
+    pub herbivores: usize,
+}
+
+impl InventoryStats {
+    pub fn new(animals: &[Animal]) -> Self {
+        let mut stats = Self {
+            animals: animals.len(),
+            ..Self::default()
+        };
+
+        // The first spelling of every normalized species
+        let mut spellings: BTreeMap<String, &str> = BTreeMap::new();
+        for animal in animals {
+            let species = spellings
+                .entry(normalize(&animal.species))
+                .or_insert(&animal.species);
+            *stats.species.entry(species.to_string()).or_insert(0) += 1;
+
+            let bucket = magnitude(animal.strength);
+            while stats.strength.len() <= bucket {
+                let (min, max) = bucket_bounds(stats.strength.len());
+                stats.strength.push(StrengthBucket {
+                    min,
+                    max,
+                    ..StrengthBucket::default()
+                });
+            }
+
+            if animal.carnivore {
+                stats.carnivores += 1;
+                stats.strength[bucket].carnivores += 1;
+            } else {
+                stats.herbivores += 1;
+                stats.strength[bucket].herbivores += 1;
+            }
+        }
+
+        stats
+    }
+
+    /// `carnivore_ratio` is the share of carnivores in the inventory.
+    pub fn carnivore_ratio(&self) -> f64 {
+        if self.animals == 0 {
+            0.0
+        } else {
+            self.carnivores as f64 / self.animals as f64
+        }
+    }
+}
+
+/// The number of decimal digits of `strength`, so that `0` gets its own bucket.
+fn magnitude(strength: usize) -> usize {
+    let mut digits = 0;
+    let mut rest = strength;
+    while rest > 0 {
+        digits += 1;
+        rest /= 10;
+    }
+    digits
+}
+
+fn bucket_bounds(bucket: usize) -> (usize, usize) {
+    match bucket {
+        0 => (0, 0),
+        _ => {
+            let min = 10usize.saturating_pow(bucket as u32 - 1);
+            (min, min.saturating_mul(10) - 1)
+        }
+    }
+}
+
+/// `CageStats` describes a single cage of a layout.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct CageStats {
+    /// Position of the cage in the layout, starting at 1
+    pub cage: usize,
+    pub kind: CageKind,
+    pub animals: usize,
+    /// Name of the strongest carnivore in the cage
+    pub predator: Option<String>,
+    /// See `Cage::safety_margin`
+    pub safety_margin: Option<i64>,
+}
+
+/// `LayoutStats` summarises a cage layout.
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+pub struct LayoutStats {
+    /// Number of cages per kind
+    pub kinds: BTreeMap<CageKind, usize>,
+    pub cages: Vec<CageStats>,
+    pub average_margin: Option<f64>,
+    pub minimum_margin: Option<i64>,
+    pub growth_spurt: usize,
+    /// Cages in which a predator can already eat a cagemate
+    pub unsafe_cages: Vec<usize>,
+    /// Cages that are one growth spurt away from being unsafe
+    pub at_risk: Vec<usize>,
+}
+
+impl LayoutStats {
+    pub fn new(cages: &[Cage], food: Option<&str>, growth_spurt: usize) -> Self {
+        let mut stats = Self {
+            growth_spurt,
+            ..Self::default()
+        };
+
+        for (i, cage) in cages.iter().enumerate() {
+            let cage_stats = CageStats {
+                cage: i + 1,
+                kind: cage.kind(),
+                animals: cage.animals.len(),
+                predator: cage.predator().map(|predator| predator.name.clone()),
+                safety_margin: cage.safety_margin(food),
+            };
+
+            *stats.kinds.entry(cage_stats.kind).or_insert(0) += 1;
+            match cage_stats.safety_margin {
+                Some(margin) if margin < 0 => stats.unsafe_cages.push(cage_stats.cage),
+                Some(margin) if margin < growth_spurt as i64 => stats.at_risk.push(cage_stats.cage),
+                _ => {}
+            }
+
+            stats.cages.push(cage_stats);
+        }
+
+        let margins: Vec<_> = stats
+            .cages
+            .iter()
+            .filter_map(|cage| cage.safety_margin)
+            .collect();
+        stats.minimum_margin = margins.iter().copied().min();
+        if !margins.is_empty() {
+            stats.average_margin = Some(margins.iter().sum::<i64>() as f64 / margins.len() as f64);
+        }
+
+        stats
+    }
+}
+
+impl Display for Stats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}\n{}", self.inventory, self.layout)
+    }
+}
+
+impl Display for InventoryStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Inventory: {} animals of {} species",
+            self.animals,
+            self.species.len()
+        )?;
+        let ratio = self.carnivore_ratio() * 100.0;
+        writeln!(f, "  carnivores: {:>4} ({:.1}%)", self.carnivores, ratio)?;
+        writeln!(
+            f,
+            "  herbivores: {:>4} ({:.1}%)",
+            self.herbivores,
+            100.0 - ratio
+        )?;
+
+        writeln!(f, "\nSpecies:")?;
+        let width = self.species.keys().map(|s| s.chars().count()).max();
+        for (species, count) in &self.species {
+            writeln!(
+                f,
+                "  {:<width$} {:>4}",
+                species,
+                count,
+                width = width.unwrap_or(0)
+            )?;
+        }
+
+        writeln!(f, "\nStrength (C = carnivore, H = herbivore):")?;
+        let labels: Vec<_> = self
+            .strength
+            .iter()
+            .map(|bucket| format!("{}..={}", bucket.min, bucket.max))
+            .collect();
+        let width = labels.iter().map(String::len).max().unwrap_or(0);
+        for (label, bucket) in labels.iter().zip(&self.strength) {
+            writeln!(
+                f,
+                "  {:>width$} {}{}",
+                label,
+                "C".repeat(bucket.carnivores),
+                "H".repeat(bucket.herbivores),
+                width = width
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Display for LayoutStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Layout: {} cages", self.cages.len())?;
+        for (kind, count) in &self.kinds {
+            writeln!(f, "  {:<10}  {:>4}", kind, count)?;
+        }
+
+        writeln!(f, "\nSafety margins (weakest prey - strongest predator):")?;
+        for cage in &self.cages {
+            if let (Some(predator), Some(margin)) = (&cage.predator, cage.safety_margin) {
+                writeln!(f, "  cage {:>3}: {:>6} ({})", cage.cage, margin, predator)?;
+            }
+        }
+        match (self.average_margin, self.minimum_margin) {
+            (Some(average), Some(minimum)) => {
+                writeln!(f, "  average: {:.1}, minimum: {}", average, minimum)?
+            }
+            _ => writeln!(f, "  no cage contains both predators and prey")?,
+        }
+
+        if !self.unsafe_cages.is_empty() {
+            writeln!(
+                f,
+                "\nUnsafe cages (a predator can eat a cagemate): {}",
+                join(&self.unsafe_cages)
+            )?;
+        }
+        if !self.at_risk.is_empty() {
+            writeln!(
+                f,
+                "\nCages at risk (margin below {}): {}",
+                self.growth_spurt,
+                join(&self.at_risk)
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+fn join(cages: &[usize]) -> String {
+    cages
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::animal::animal;
+
+    #[test]
+    fn inventory_stats() {
+        let animals = [
+            animal("A1", "S1", 0, false),
+            animal("A2", "S1", 9, false),
+            animal("B1", "S2", 10, true),
+            animal("B2", "S2", 1000, true),
+            animal("B3", " s2", 20, true),
+        ];
+
+        let stats = InventoryStats::new(&animals);
+
+        assert_eq!(stats.carnivores, 3);
+        assert_eq!(stats.herbivores, 2);
+        assert_eq!(stats.species["S1"], 2);
+        assert_eq!(stats.species["S2"], 3);
+        assert_eq!(stats.species.len(), 2);
+        assert_eq!(stats.strength.len(), 5);
+        assert_eq!((stats.strength[1].min, stats.strength[1].max), (1, 9));
+        assert_eq!(stats.strength[1].herbivores, 1);
+        assert_eq!(stats.strength[3].carnivores, 0);
+        assert_eq!(stats.strength[4].carnivores, 1);
+    }
+
+    #[test]
+    fn layout_stats() {
+        let cages = [
+            Cage::from(vec![
+                animal("A1", "S1", 10, true),
+                animal("B1", "S2", 12, false),
+            ]),
+            Cage::from(vec![
+                animal("C1", "S3", 20, true),
+                animal("D1", "S4", 40, false),
+            ]),
+            Cage::from(vec![
+                animal("E1", "S5", 10, true),
+                animal("F1", "S6", 5, true),
+            ]),
+            Cage::from(vec![animal("G1", "S7", 1, false)]),
+        ];
+
+        let stats = LayoutStats::new(&cages, None, 5);
+
+        assert_eq!(stats.kinds[&CageKind::Mixed], 2);
+        assert_eq!(stats.kinds[&CageKind::Carnivores], 1);
+        assert_eq!(stats.kinds[&CageKind::Herbivores], 1);
+        assert_eq!(stats.minimum_margin, Some(-5));
+        assert_eq!(stats.average_margin, Some(17.0 / 3.0));
+        assert_eq!(stats.unsafe_cages, [3]);
+        assert_eq!(stats.at_risk, [1]);
+
+        // The food in the feeding cage isn't prey
+        let feeding = [Cage::from(vec![
+            animal("A1", "S1", 10, true),
+            animal("B1", "S2", 10, false),
+            animal("H1", "Food", 2, false),
+        ])];
+        let stats = LayoutStats::new(&feeding, Some("food"), 5);
+        assert_eq!(stats.minimum_margin, Some(0));
+        assert!(stats.unsafe_cages.is_empty());
+        assert_eq!(LayoutStats::new(&feeding, None, 5).unsafe_cages, [1]);
+    }
+}
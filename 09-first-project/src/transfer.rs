@@ -0,0 +1,416 @@
+//! Planning how to move the animals from one cage layout to another.
+//!
+//! Animals are moved in a transport crate, which can only carry so much. At no
+//! time may a predator be left with an animal it would eat, neither in the
+//! crate nor in a cage. Where the animals block each other, e.g. when two of
+//! them have to swap cages, they wait in a temporary holding pen.
+
+use crate::diff;
+use crate::error::Error;
+use crate::invariants::eats;
+use crate::species::same_species;
+use crate::{Animal, Cage, Result};
+use serde::Serialize;
+use std::fmt::{self, Display, Formatter};
+
+/// `Transport` is what the transport crate can carry on a single trip.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Transport {
+    /// The total strength of the animals in the crate
+    pub max_strength: usize,
+    /// The number of animals in the crate, `None` for no limit
+    pub max_animals: Option<usize>,
+}
+
+/// `Location` is where an animal is before or after a trip.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Location {
+    /// Outside the zoo, for arrivals and departures
+    Outside,
+    /// A cage, numbered like the current layout starting at 1. Cages the
+    /// current layout doesn't have get the numbers after its last cage.
+    Cage(usize),
+    /// A temporary holding pen, starting at 1
+    Holding(usize),
+}
+
+/// `Trip` is a single ride of the transport crate.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct Trip {
+    pub animals: Vec<Animal>,
+    pub from: Location,
+    pub to: Location,
+}
+
+/// `Schedule` is the result of `plan`.
+#[derive(Serialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Schedule {
+    /// The trips, in the order they have to be made
+    pub trips: Vec<Trip>,
+    /// The cage number of every cage of the target layout
+    pub cages: Vec<usize>,
+}
+
+/// `plan` computes the trips that turn the `current` layout into the `target`
+/// layout.
+///
+/// Every cage of the target layout stays where the most similar cage of the
+/// current layout is, like in `LayoutDiff`. Animals only in the current layout
+/// leave the zoo, and animals only in the target layout arrive. Animals that
+/// travel together come from the same place and go to the same place, and no
+/// trip puts a predator next to its prey. Predators and prey that share a
+/// cage in the target layout, e.g. the food, are the exception: they may
+/// meet in their target cage.
+///
+/// # Errors
+///
+/// This function errors with `Error::TooHeavy` if an animal is too strong to
+/// be transported at all, and with `Error::InvalidValue` if the crate can't
+/// carry any animal.
+pub fn plan(current: &[Cage], target: &[Cage], transport: Transport) -> Result<Schedule> {
+    if transport.max_animals == Some(0) {
+        return Err(Error::InvalidValue(String::from(
+            "the transport crate has to carry at least one animal",
+        )));
+    }
+
+    // Target cages without a match get new cages after the current ones
+    let mut next = current.len();
+    let cages: Vec<usize> = diff::matching(current, target)
+        .into_iter()
+        .map(|matched| {
+            matched.unwrap_or_else(|| {
+                next += 1;
+                next - 1
+            })
+        })
+        .collect();
+
+    let mut state = State {
+        places: std::iter::once(Location::Outside)
+            .chain((1..=next).map(Location::Cage))
+            .map(|location| (location, vec![]))
+            .collect(),
+        destinations: vec![],
+    };
+    for (i, cage) in current.iter().enumerate() {
+        for animal in &cage.animals {
+            let destination = target
+                .iter()
+                .zip(&cages)
+                .find(|(cage, _)| cage.animals.iter().any(|a| same_animal(a, animal)))
+                .map_or(0, |(_, &j)| j + 1);
+            state.arrive(i + 1, animal.clone(), destination);
+        }
+    }
+    for (cage, &j) in target.iter().zip(&cages) {
+        for animal in &cage.animals {
+            if !current
+                .iter()
+                .flat_map(|c| &c.animals)
+                .any(|a| same_animal(a, animal))
+            {
+                state.arrive(0, animal.clone(), j + 1);
+            }
+        }
+    }
+
+    for (_, animals) in &state.places {
+        for (animal, _) in animals {
+            if animal.strength > transport.max_strength {
+                return Err(Error::TooHeavy {
+                    animal: Box::new(animal.clone()),
+                    max_strength: transport.max_strength,
+                });
+            }
+        }
+    }
+
+    let mut trips = vec![];
+    loop {
+        let pending: Vec<(usize, usize)> = state.pending().collect();
+        if pending.is_empty() {
+            break;
+        }
+
+        let trip = pending
+            .iter()
+            .find_map(|&(from, i)| state.trip(from, i, transport, false));
+        let trip = match trip {
+            Some(trip) => trip,
+            None => {
+                // Everyone is blocked. Clear the way by moving some animals
+                // into a holding pen, which they only leave for their
+                // target cage.
+                let &(from, i) = pending
+                    .iter()
+                    .find(|&&(from, _)| !matches!(state.places[from].0, Location::Holding(_)))
+                    .expect("animals in holding pens can always move on");
+                let holding = state
+                    .places
+                    .iter()
+                    .filter(|(location, _)| matches!(location, Location::Holding(_)))
+                    .count();
+                state.places.push((Location::Holding(holding + 1), vec![]));
+                state
+                    .trip(from, i, transport, true)
+                    .expect("an empty holding pen takes anyone")
+            }
+        };
+        trips.push(trip);
+    }
+
+    Ok(Schedule {
+        trips,
+        cages: cages.into_iter().map(|j| j + 1).collect(),
+    })
+}
+
+fn same_animal(a: &Animal, b: &Animal) -> bool {
+    a.name == b.name && same_species(&a.species, &b.species)
+}
+
+/// Where every animal is during the planning.
+struct State {
+    /// The places animals can be, with the animals there and the index of the
+    /// place each of them has to go to
+    places: Vec<(Location, Vec<(Animal, usize)>)>,
+    /// The animals sharing a place in the target layout, by their destination
+    destinations: Vec<(usize, Animal)>,
+}
+
+impl State {
+    fn arrive(&mut self, place: usize, animal: Animal, destination: usize) {
+        self.destinations.push((destination, animal.clone()));
+        self.places[place].1.push((animal, destination));
+    }
+
+    /// The animals that aren't at their destination yet, as the index of
+    /// their place and their index in it.
+    fn pending(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.places
+            .iter()
+            .enumerate()
+            .flat_map(|(from, (_, animals))| {
+                animals
+                    .iter()
+                    .enumerate()
+                    .filter(move |(_, (_, to))| *to != from)
+                    .map(move |(i, _)| (from, i))
+            })
+    }
+
+    /// `allowed` tells if `a` and `b` may share `place`, because they live
+    /// together in the target layout.
+    fn allowed(&self, place: usize, a: &Animal, b: &Animal) -> bool {
+        let target = |animal: &Animal| {
+            self.destinations
+                .iter()
+                .any(|(to, other)| *to == place && same_animal(other, animal))
+        };
+        target(a) && target(b)
+    }
+
+    /// Moves the `i`th animal at `from` and as many of its neighbours with the
+    /// same destination as safely possible. With `holding`, they go into the
+    /// last holding pen instead.
+    fn trip(&mut self, from: usize, i: usize, transport: Transport, holding: bool) -> Option<Trip> {
+        let to = if holding {
+            self.places.len() - 1
+        } else {
+            self.places[from].1[i].1
+        };
+        let outside = |place: usize| self.places[place].0 == Location::Outside;
+        let fine = |a: &Animal, b: &Animal, place: usize| {
+            (!eats(a, b) && !eats(b, a)) || self.allowed(place, a, b)
+        };
+
+        let mut group: Vec<usize> = vec![];
+        let mut strength = 0;
+        let animals = &self.places[from].1;
+        let candidates =
+            std::iter::once(i)
+                .chain((0..animals.len()).filter(|&j| {
+                    j != i && animals[j].1 != from && (holding || animals[j].1 == to)
+                }));
+        for j in candidates {
+            let animal = &animals[j].0;
+            let full = transport.max_animals.is_some_and(|max| group.len() >= max);
+            let safe_in_crate = group
+                .iter()
+                .all(|&k| !eats(animal, &animals[k].0) && !eats(&animals[k].0, animal));
+            let safe_there = outside(to)
+                || self.places[to]
+                    .1
+                    .iter()
+                    .all(|(other, _)| fine(animal, other, to));
+            if !full
+                && strength + animal.strength <= transport.max_strength
+                && safe_in_crate
+                && safe_there
+            {
+                group.push(j);
+                strength += animal.strength;
+            }
+        }
+        if group.is_empty() {
+            return None;
+        }
+
+        // Remove from the back, so that the indices stay valid
+        group.sort_unstable();
+        let mut moved: Vec<_> = group
+            .iter()
+            .rev()
+            .map(|&j| self.places[from].1.remove(j))
+            .collect();
+        moved.reverse();
+        let trip = Trip {
+            animals: moved.iter().map(|(animal, _)| animal.clone()).collect(),
+            from: self.places[from].0,
+            to: self.places[to].0,
+        };
+        self.places[to].1.extend(moved);
+        Some(trip)
+    }
+}
+
+impl Display for Location {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Outside => write!(f, "outside"),
+            Self::Cage(cage) => write!(f, "cage {}", cage),
+            Self::Holding(pen) => write!(f, "holding pen {}", pen),
+        }
+    }
+}
+
+impl Display for Trip {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let animals: Vec<_> = self
+            .animals
+            .iter()
+            .map(|animal| format!("{} ({})", animal.name, animal.species))
+            .collect();
+        write!(f, "{} -> {}: {}", self.from, self.to, animals.join(", "))
+    }
+}
+
+impl Display for Schedule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.trips.is_empty() {
+            return writeln!(f, "no animal has to move");
+        }
+        for (i, trip) in self.trips.iter().enumerate() {
+            writeln!(f, "{:>3}. {}", i + 1, trip)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::animal::animal;
+
+    fn names(trip: &Trip) -> Vec<&str> {
+        trip.animals
+            .iter()
+            .map(|animal| animal.name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn swap_through_holding_pen() {
+        let leo = animal("Leo", "Lion", 30, true);
+        let nala = animal("Nala", "Lion", 25, true);
+        let bambi = animal("Bambi", "Deer", 28, false);
+        let dumbo = animal("Dumbo", "Elephant", 50, false);
+        let current = [
+            Cage::from(vec![leo.clone(), nala.clone()]),
+            Cage::from(vec![bambi.clone(), dumbo.clone()]),
+        ];
+        let target = [
+            Cage::from(vec![nala, bambi]),
+            Cage::from(vec![leo, dumbo, animal("Marty", "Zebra", 40, false)]),
+        ];
+        let transport = Transport {
+            max_strength: 100,
+            max_animals: None,
+        };
+
+        let schedule = plan(&current, &target, transport).unwrap();
+
+        assert_eq!(schedule.cages, [1, 2]);
+        let trips: Vec<_> = schedule
+            .trips
+            .iter()
+            .map(|trip| (names(trip), trip.from, trip.to))
+            .collect();
+        assert_eq!(
+            trips,
+            [
+                (vec!["Marty"], Location::Outside, Location::Cage(2)),
+                (vec!["Leo"], Location::Cage(1), Location::Holding(1)),
+                (vec!["Bambi"], Location::Cage(2), Location::Cage(1)),
+                (vec!["Leo"], Location::Holding(1), Location::Cage(2)),
+            ]
+        );
+    }
+
+    #[test]
+    fn transport_capacity() {
+        let current = [Cage::from(vec![
+            animal("Marty", "Zebra", 20, false),
+            animal("Zora", "Zebra", 15, false),
+            animal("Zed", "Zebra", 10, false),
+        ])];
+        let target = [Cage::from(vec![animal("Marty", "Zebra", 20, false)])];
+        let transport = Transport {
+            max_strength: 30,
+            max_animals: Some(1),
+        };
+
+        let schedule = plan(&current, &target, transport).unwrap();
+        assert_eq!(schedule.trips.len(), 2);
+
+        let schedule = plan(
+            &current,
+            &target,
+            Transport {
+                max_animals: None,
+                ..transport
+            },
+        )
+        .unwrap();
+        assert_eq!(schedule.trips.len(), 1);
+        assert_eq!(names(&schedule.trips[0]), ["Zora", "Zed"]);
+        assert_eq!(schedule.trips[0].to, Location::Outside);
+
+        assert!(matches!(
+            plan(
+                &current,
+                &target,
+                Transport {
+                    max_strength: 12,
+                    ..transport
+                }
+            ),
+            Err(Error::TooHeavy { .. })
+        ));
+    }
+
+    #[test]
+    fn empty_crate() {
+        let current = [Cage::from(vec![animal("Marty", "Zebra", 20, false)])];
+        let transport = Transport {
+            max_strength: 30,
+            max_animals: Some(0),
+        };
+
+        assert!(matches!(
+            plan(&current, &[], transport),
+            Err(Error::InvalidValue(_))
+        ));
+    }
+}
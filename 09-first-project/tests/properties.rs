@@ -0,0 +1,176 @@
+//! Property based tests for `cage_em_all`.
+//!
+//! Random inventories are caged and every resulting layout is checked with
+//! `my_zoo::invariants::check`. If an inventory breaks an invariant, proptest
+//! shrinks it to a minimal counterexample, which is written to
+//! `tests/fixtures/`. Every fixture in there is replayed by the `fixtures` test,
+//! so once a bug is fixed, the counterexample stays around as a regression test.
+
+use my_zoo::generate::{Generator, Shape};
+use my_zoo::invariants::check;
+use my_zoo::{cage_em_all, Animal, Error};
+use proptest::prelude::*;
+use proptest::test_runner::{Config, TestCaseError, TestError, TestRunner};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::ffi::OsStr;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+/// `Fixture` is the input of a single call to `cage_em_all`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct Fixture {
+    food: Option<String>,
+    animals: Vec<Animal>,
+}
+
+fn fixture_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+}
+
+/// An inventory in which every species has a consistent diet. Strengths are
+/// kept small, so that equally strong animals are common.
+fn inventory() -> impl Strategy<Value = Vec<Animal>> {
+    prop::collection::vec(
+        (any::<bool>(), prop::collection::vec(0..30usize, 1..4)),
+        0..8,
+    )
+    .prop_map(|species| {
+        species
+            .into_iter()
+            .enumerate()
+            .flat_map(|(i, (carnivore, strengths))| {
+                strengths
+                    .into_iter()
+                    .enumerate()
+                    .map(move |(j, strength)| Animal {
+                        name: format!("A{}-{}", i, j),
+                        species: format!("S{}", i),
+                        strength,
+                        carnivore,
+                        ..Animal::default()
+                    })
+            })
+            .collect()
+    })
+}
+
+/// An inventory and, sometimes, one of its species as food.
+fn fixture() -> impl Strategy<Value = Fixture> {
+    (inventory(), any::<prop::sample::Index>(), any::<bool>()).prop_map(|(animals, index, feed)| {
+        Fixture {
+            food: if feed && !animals.is_empty() {
+                Some(index.get(&animals).species.clone())
+            } else {
+                None
+            },
+            animals,
+        }
+    })
+}
+
+/// The property: `cage_em_all` either produces a valid layout, or rejects the
+/// food for a good reason.
+fn cages_are_valid(fixture: &Fixture) -> Result<(), TestCaseError> {
+    let food = fixture.food.as_deref();
+    match cage_em_all(fixture.animals.clone(), food) {
+        Ok(cages) => {
+            let violations = check(&fixture.animals, food, &cages);
+            prop_assert!(
+                violations.is_empty(),
+                "{}",
+                violations
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            );
+        }
+        Err(Error::FoodTooStrong { .. }) | Err(Error::NoCarnivore(_)) => {
+            prop_assert!(food.is_some())
+        }
+        Err(err) => return Err(TestCaseError::fail(err.to_string())),
+    }
+    Ok(())
+}
+
+/// Writes the counterexample to the fixture directory and returns its path.
+fn write_fixture(fixture: &Fixture) -> PathBuf {
+    let json = serde_json::to_string_pretty(fixture).unwrap();
+    let mut hasher = DefaultHasher::new();
+    json.hash(&mut hasher);
+
+    let path = fixture_dir().join(format!("counterexample-{:016x}.json", hasher.finish()));
+    fs::create_dir_all(fixture_dir()).unwrap();
+    fs::write(&path, json).unwrap();
+    path
+}
+
+/// Runs `cages_are_valid` on the fixtures generated by `strategy` and writes
+/// the minimal counterexample to the fixture directory, if there is one.
+fn run_property(strategy: impl Strategy<Value = Fixture>) {
+    let mut runner = TestRunner::new(Config {
+        cases: 512,
+        // The counterexamples are persisted as JSON fixtures instead
+        failure_persistence: None,
+        ..Config::default()
+    });
+
+    match runner.run(&strategy, |fixture| cages_are_valid(&fixture)) {
+        Ok(()) => {}
+        Err(TestError::Fail(reason, fixture)) => panic!(
+            "{}\nminimal counterexample written to {}",
+            reason,
+            write_fixture(&fixture).display()
+        ),
+        Err(TestError::Abort(reason)) => panic!("{}", reason),
+    }
+}
+
+#[test]
+fn caging_invariants() {
+    run_property(fixture());
+}
+
+#[test]
+fn generated_inventories() {
+    let shape = prop_oneof![
+        Just(Shape::Realistic),
+        Just(Shape::EqualCarnivores),
+        Just(Shape::NearMisses),
+    ];
+    run_property(
+        (any::<u64>(), 0..20usize, shape).prop_map(|(seed, species, shape)| Fixture {
+            food: None,
+            animals: Generator {
+                seed,
+                species,
+                shape,
+                ..Generator::default()
+            }
+            .generate(),
+        }),
+    );
+}
+
+#[test]
+fn fixtures() {
+    let entries = match fs::read_dir(fixture_dir()) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+
+    for entry in entries {
+        let path = entry.unwrap().path();
+        if path.extension() != Some(OsStr::new("json")) {
+            continue;
+        }
+        let fixture: Fixture = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        if let Err(err) = cages_are_valid(&fixture) {
+            panic!("{}: {}", path.display(), err);
+        }
+    }
+}